@@ -0,0 +1,95 @@
+use std::process::Stdio;
+use std::time::{Duration, Instant, SystemTime};
+use colored::*;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use crate::output::{JobOutput, Stream};
+use crate::results::Capture;
+use crate::{ExecOptions, JobResult, JobStatus, State};
+
+/// Runs a job on the --async runtime, like `execute_command` but without a thread per job.
+pub async fn execute(command: &str, command_idx: usize, options: ExecOptions, output: &JobOutput<'_>, capture: &Capture, state: &State, argv: Option<&[String]>) -> JobResult {
+    let start = SystemTime::now();
+    let started = Instant::now();
+    let write = crate::output_writer(command_idx, &options, output, capture, state);
+    let stdio = |discard: bool| if discard && !capture.active() && !options.until_match { Stdio::null() } else { Stdio::piped() };
+    let mut program = match argv {
+        Some(argv) => {
+            let mut program = Command::new(&argv[0]);
+            program.args(&argv[1..]);
+            program
+        }
+        None => {
+            let mut program = Command::new("sh");
+            program.arg("-c").arg(command);
+            program
+        }
+    };
+    let mut child = match program
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(stdio(options.silent || options.no_stdout))
+        .stderr(stdio(options.silent || options.no_stderr))
+        .spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("{} Failed to execute `{}`: {}", "warning:".yellow().bold(), command, err);
+            return JobResult { status: JobStatus::Failed, start, runtime: started.elapsed() };
+        }
+    };
+
+    // tokio only forgets the pid of a child it already reaped, which is then no longer there to kill.
+    let pid = child.id();
+    if let Some(pid) = pid {
+        state.register(command_idx, pid, command);
+    }
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let wait = async {
+        let Some(timeout) = options.timeout else {
+            return child.wait().await.map(|status| (status, false));
+        };
+        match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status.map(|status| (status, false)),
+            Err(_) => {
+                if let Some(pid) = pid {
+                    unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+                }
+                child.wait().await.map(|status| (status, true))
+            }
+        }
+    };
+    let (_, _, result) = tokio::join!(
+        stream_output(stdout, |chunk| write(Stream::Stdout, chunk)),
+        stream_output(stderr, |chunk| write(Stream::Stderr, chunk)),
+        wait,
+    );
+    state.unregister(command_idx);
+    crate::job_result(command, command_idx, &options, output, result, start, started.elapsed())
+}
+
+/// Passes every chunk of `reader` to `write` and `None` once it is closed, if the stream was piped. `write` can wait
+/// for the output collector or the disk, so it runs with `block_in_place` to let the other tasks move to another thread.
+async fn stream_output(reader: Option<impl AsyncRead + Unpin>, write: impl Fn(Option<&[u8]>)) {
+    let Some(mut reader) = reader else {
+        return;
+    };
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => tokio::task::block_in_place(|| write(Some(&buf[..n]))),
+        }
+    }
+    tokio::task::block_in_place(|| write(None));
+}
+
+/// Sleeps without blocking a thread of the runtime with --async.
+pub async fn sleep(asynchronous: bool, duration: Duration) {
+    if asynchronous {
+        tokio::time::sleep(duration).await;
+    }
+    else {
+        std::thread::sleep(duration);
+    }
+}
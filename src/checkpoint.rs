@@ -0,0 +1,81 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub struct Checkpoint {
+    pub command: String,
+    /// The `Debug` form of the `Selection`, since --offset, --shuffle and the like change what `next` means.
+    pub selection: String,
+    pub total: usize,
+    pub next: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub pending: Vec<(usize, usize)>,
+}
+
+impl Checkpoint {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Option<Checkpoint>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid line '{}'", line));
+        let mut checkpoint = Checkpoint { command: String::new(), selection: String::new(), total: 0, next: 0, completed: 0, failed: 0, pending: vec![] };
+        for line in contents.lines() {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            let number = || value.parse::<usize>().map_err(|_| invalid(line));
+            match key {
+                "command" => checkpoint.command = value.to_string(),
+                "selection" => checkpoint.selection = value.to_string(),
+                "total" => checkpoint.total = number()?,
+                "next" => checkpoint.next = number()?,
+                "completed" => checkpoint.completed = number()?,
+                "failed" => checkpoint.failed = number()?,
+                "pending" => {
+                    for job in value.split(',').filter(|job| !job.is_empty()) {
+                        let (job, attempt) = job.split_once(':').ok_or_else(|| invalid(line))?;
+                        let job = job.parse().map_err(|_| invalid(line))?;
+                        let attempt = attempt.parse().map_err(|_| invalid(line))?;
+                        checkpoint.pending.push((job, attempt));
+                    }
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes to a temporary file first so a crash never leaves a truncated checkpoint behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let pending: Vec<String> = self.pending.iter().map(|(job, attempt)| format!("{}:{}", job, attempt)).collect();
+        let mut file = File::create(&tmp)?;
+        write!(file, "command={}\nselection={}\ntotal={}\nnext={}\ncompleted={}\nfailed={}\npending={}\n",
+            self.command, self.selection, self.total, self.next, self.completed, self.failed, pending.join(","))?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_checkpoints_load_back() {
+        let path = std::env::temp_dir().join(format!("parel-checkpoint-{}", std::process::id()));
+        assert!(Checkpoint::load(&path).unwrap().is_none());
+        let checkpoint = Checkpoint { command: "\"echo W\"".to_string(), selection: "Selection".to_string(), total: 9, next: 5, completed: 4, failed: 1, pending: vec![(2, 0), (3, 1)] };
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!((loaded.command, loaded.selection, loaded.total, loaded.next), (checkpoint.command, checkpoint.selection, 9, 5));
+        assert_eq!((loaded.completed, loaded.failed, loaded.pending), (4, 1, checkpoint.pending));
+        std::fs::write(&path, "next=5\nnext\n").unwrap();
+        let err = Checkpoint::load(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.to_string(), "invalid line 'next'");
+    }
+}
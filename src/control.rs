@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use crate::State;
+
+/// Runs one control command and returns the reply, which always ends with a newline.
+fn execute(line: &str, state: &State, total: usize) -> String {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("status"), None, _) => {
+            let mut running: Vec<usize> = state.running.lock().unwrap().keys().copied().collect();
+            running.sort_unstable();
+            let running: Vec<String> = running.iter().map(usize::to_string).collect();
+            format!("total={}\ncompleted={}\nfailed={}\nskipped={}\nthreads={}\npaused={}\nrunning={}\n",
+                total, state.completed.load(Ordering::SeqCst), state.failed.load(Ordering::SeqCst),
+                state.skipped.load(Ordering::SeqCst), state.threads.load(Ordering::SeqCst),
+                state.paused.load(Ordering::SeqCst), running.join(","))
+        }
+        (Some("pause"), None, _) => {
+            state.paused.store(true, Ordering::SeqCst);
+            "ok\n".to_string()
+        }
+        (Some("resume"), None, _) => {
+            state.paused.store(false, Ordering::SeqCst);
+            "ok\n".to_string()
+        }
+        (Some("set-threads"), Some(threads), None) => match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => {
+                state.threads.store(threads, Ordering::SeqCst);
+                "ok\n".to_string()
+            }
+            _ => format!("error: invalid number of threads '{}'\n", threads),
+        },
+        (Some("abort"), None, _) => {
+            state.abort();
+            "ok\n".to_string()
+        }
+        _ => format!("error: unknown command '{}'\n", line.trim()),
+    }
+}
+
+fn handle(stream: UnixStream, state: &State, total: usize) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writer.write_all(execute(&line, state, total).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Listens on `path`, replacing a stale socket left behind by a parel that didn't exit cleanly.
+pub fn serve(path: &str, state: Arc<State>, total: usize) -> io::Result<()> {
+    let listener = match UnixListener::bind(path) {
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse && UnixStream::connect(path).is_err() => {
+            fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        result => result?,
+    };
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let state = state.clone();
+            thread::spawn(move || handle(stream, &state, total));
+        }
+    });
+    Ok(())
+}
+
+pub fn remove<P: AsRef<Path>>(path: P) {
+    let _ = fs::remove_file(path);
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use rusqlite::{params, Connection};
+use crate::JobResult;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY,
+    job INTEGER NOT NULL,
+    command TEXT NOT NULL,
+    status TEXT NOT NULL,
+    exitval INTEGER NOT NULL,
+    signal INTEGER NOT NULL,
+    start REAL NOT NULL,
+    runtime REAL NOT NULL,
+    stdout BLOB NOT NULL,
+    stderr BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS job_values (
+    job_id INTEGER NOT NULL REFERENCES jobs(id),
+    identifier TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS jobs_job ON jobs(job);
+CREATE INDEX IF NOT EXISTS job_values_job_id ON job_values(job_id);
+";
+
+pub struct Database {
+    connection: Mutex<Connection>,
+}
+
+impl Database {
+    /// Opens or creates the database; WAL keeps a per-job commit from syncing the whole file.
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Database> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Database { connection: Mutex::new(connection) })
+    }
+
+    pub fn record(&self, job: usize, result: &JobResult, command: &str, values: &[(&str, &str)], output: &[Vec<u8>; 2]) -> rusqlite::Result<()> {
+        let (exitval, signal) = result.status.exit_signal();
+        let start = result.start.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO jobs (job, command, status, exitval, signal, start, runtime, stdout, stderr) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![job as i64, command, result.status.to_string(), exitval, signal, start, result.runtime.as_secs_f64(), output[0], output[1]],
+        )?;
+        let id = transaction.last_insert_rowid();
+        for (identifier, value) in values {
+            transaction.execute("INSERT INTO job_values (job_id, identifier, value) VALUES (?1, ?2, ?3)", params![id, identifier, value])?;
+        }
+        transaction.commit()
+    }
+}
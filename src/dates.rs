@@ -0,0 +1,177 @@
+use std::ffi::CString;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+/// One date every `unit` from `start` up to but without `end`, for --dates. Dates are in UTC.
+#[derive(Debug, Clone)]
+pub struct Dates {
+    start: Civil,
+    unit: Unit,
+    len: usize,
+    format: CString,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Civil {
+    year: i64,
+    month: i64,
+    day: i64,
+}
+
+impl Dates {
+    /// `range` is `start..end`, `unit` one of hour, day, week or month and `format` a strftime pattern.
+    pub fn parse(range: &str, unit: Option<&str>, format: Option<&str>) -> Result<Dates, String> {
+        let (start, end) = range.split_once("..").ok_or(format!("'{}' is not of the form start..end", range))?;
+        let (start, end) = (parse_civil(start)?, parse_civil(end)?);
+        let unit = match unit.unwrap_or("day") {
+            "hour" => Unit::Hour,
+            "day" => Unit::Day,
+            "week" => Unit::Week,
+            "month" => Unit::Month,
+            unit => return Err(format!("unknown unit '{}', expected hour, day, week or month", unit)),
+        };
+        let format = format.unwrap_or(match unit {
+            Unit::Hour => "%Y-%m-%dT%H:%M",
+            Unit::Day | Unit::Week => "%Y-%m-%d",
+            Unit::Month => "%Y-%m",
+        });
+        let format = CString::new(format).map_err(|_| "the format can't contain a NUL byte".to_string())?;
+        let mut dates = Dates { start, unit, len: 0, format };
+        let (from, to) = (start.timestamp(), end.timestamp());
+        if to > from {
+            let len = match unit {
+                Unit::Hour => (to - from) / HOUR,
+                Unit::Day => (to - from) / DAY,
+                Unit::Week => (to - from + 7 * DAY - 1) / (7 * DAY),
+                Unit::Month => {
+                    // Months differ in length, so this counts from the calendar and corrects for clamped days.
+                    let mut last = (end.year * 12 + end.month) - (start.year * 12 + start.month);
+                    while dates.timestamp((last + 1) as usize) < to {
+                        last += 1;
+                    }
+                    while last > 0 && dates.timestamp(last as usize) >= to {
+                        last -= 1;
+                    }
+                    last + 1
+                }
+            };
+            dates.len = usize::try_from(len).map_err(|_| format!("{} has too many dates", range))?;
+        }
+        Ok(dates)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get(&self, line: usize) -> String {
+        let time = self.timestamp(line) as libc::time_t;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        let mut buf = [0u8; 256];
+        let len = unsafe {
+            libc::gmtime_r(&time, &mut tm);
+            libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), self.format.as_ptr(), &tm)
+        };
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    }
+
+    /// Seconds since the epoch of the `line`th date, a month later keeps the day unless the month is shorter.
+    fn timestamp(&self, line: usize) -> i64 {
+        let line = line as i64;
+        match self.unit {
+            Unit::Hour => self.start.timestamp() + line * HOUR,
+            Unit::Day => self.start.timestamp() + line * DAY,
+            Unit::Week => self.start.timestamp() + line * 7 * DAY,
+            Unit::Month => {
+                let months = self.start.year * 12 + self.start.month - 1 + line;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+                let day = self.start.day.min(days_in_month(year, month));
+                Civil { year, month, day }.timestamp()
+            }
+        }
+    }
+}
+
+impl Civil {
+    fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * DAY
+    }
+}
+
+/// `YYYY-MM-DD`, the hours of --dates with hour start at midnight.
+fn parse_civil(value: &str) -> Result<Civil, String> {
+    let invalid = || format!("'{}' is not a date like 2024-01-31", value);
+    let mut parts = value.splitn(3, '-').map(|part| part.parse::<i64>().map_err(|_| invalid()));
+    let (year, month, day) = (parts.next().ok_or_else(invalid)??, parts.next().ok_or_else(invalid)??, parts.next().ok_or_else(invalid)??);
+    if !(0..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(invalid());
+    }
+    Ok(Civil { year, month, day })
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all(dates: &Dates) -> Vec<String> {
+        (0..dates.len()).map(|line| dates.get(line)).collect()
+    }
+
+    #[test]
+    fn days_include_the_start_but_not_the_end() {
+        let dates = Dates::parse("2024-02-27..2024-03-02", None, None).unwrap();
+        assert_eq!(all(&dates), ["2024-02-27", "2024-02-28", "2024-02-29", "2024-03-01"]);
+    }
+
+    #[test]
+    fn hours_start_at_midnight() {
+        let dates = Dates::parse("2024-01-01..2024-01-02", Some("hour"), None).unwrap();
+        assert_eq!(dates.len(), 24);
+        assert_eq!(dates.get(23), "2024-01-01T23:00");
+    }
+
+    #[test]
+    fn weeks_cover_a_partial_last_week() {
+        let dates = Dates::parse("2024-01-01..2024-01-16", Some("week"), None).unwrap();
+        assert_eq!(all(&dates), ["2024-01-01", "2024-01-08", "2024-01-15"]);
+    }
+
+    #[test]
+    fn months_keep_the_day_unless_the_month_is_shorter() {
+        let dates = Dates::parse("2024-01-31..2024-05-01", Some("month"), Some("%Y-%m-%d")).unwrap();
+        assert_eq!(all(&dates), ["2024-01-31", "2024-02-29", "2024-03-31", "2024-04-30"]);
+        assert_eq!(all(&Dates::parse("2024-01-15..2024-03-15", Some("month"), None).unwrap()), ["2024-01", "2024-02"]);
+    }
+
+    #[test]
+    fn empty_and_invalid_ranges() {
+        assert_eq!(Dates::parse("2024-03-01..2024-01-01", None, None).unwrap().len(), 0);
+        assert!(Dates::parse("2023-02-29..2023-03-01", None, None).is_err());
+        assert!(Dates::parse("2024-01-01", None, None).is_err());
+        assert!(Dates::parse("2024-01-01..2024-02-01", Some("year"), None).is_err());
+    }
+}
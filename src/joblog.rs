@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use crate::JobResult;
+
+const HEADER: &str = "Seq\tHost\tStarttime\tJobRuntime\tSend\tReceive\tExitval\tSignal";
+
+pub struct JobLog {
+    file: Mutex<File>,
+}
+
+impl JobLog {
+    /// `identifiers` name the columns of the substituted values in the header.
+    pub fn open<P: AsRef<Path>>(path: P, identifiers: &[&str]) -> io::Result<JobLog> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            let identifiers: String = identifiers.iter().map(|identifier| format!("\t{}", escape(identifier))).collect();
+            writeln!(file, "{}{}\tCommand", HEADER, identifiers)?;
+        }
+        Ok(JobLog { file: Mutex::new(file) })
+    }
+
+    /// Appends one line in GNU parallel's joblog format, with the substituted values before the command so it stays last.
+    pub fn record(&self, seq: usize, result: &JobResult, command: &str, values: &[&str]) -> io::Result<()> {
+        let start = result.start.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let (exitval, signal) = result.status.exit_signal();
+        let mut line = format!("{}\t:\t{:.3}\t{:.3}\t0\t0\t{}\t{}", seq, start, result.runtime.as_secs_f64(), exitval, signal);
+        for value in values {
+            line.push('\t');
+            line.push_str(&escape(value));
+        }
+        line.push('\t');
+        line.push_str(&escape(command));
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// Reads the exit value and signal of every job recorded in a joblog, keeping the latest entry per job.
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<HashMap<usize, (i32, i32)>> {
+    let mut jobs = HashMap::new();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(jobs),
+        Err(err) => return Err(err),
+    };
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.splitn(9, '\t').collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let (Ok(seq), Ok(exitval), Ok(signal)) = (fields[0].parse(), fields[6].parse(), fields[7].parse()) else {
+            continue;
+        };
+        jobs.insert(seq, (exitval, signal));
+    }
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use crate::JobStatus;
+
+    #[test]
+    fn recorded_jobs_are_read_back() {
+        let path = std::env::temp_dir().join(format!("parel-joblog-{}", std::process::id()));
+        let joblog = JobLog::open(&path, &["W"]).unwrap();
+        for (seq, status) in [(0, JobStatus::Exited(1)), (1, JobStatus::Signaled(9)), (0, JobStatus::Exited(0))] {
+            let result = JobResult { status, start: SystemTime::now(), runtime: Duration::from_millis(5) };
+            joblog.record(seq, &result, "echo a\tb", &["a\tb"]).unwrap();
+        }
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines[0], "Seq\tHost\tStarttime\tJobRuntime\tSend\tReceive\tExitval\tSignal\tW\tCommand");
+        assert!(lines[1].starts_with("0\t:\t") && lines[1].ends_with("\t0.005\t0\t0\t1\t0\ta\\tb\techo a\\tb"));
+        let jobs = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(jobs, HashMap::from([(0, (0, 0)), (1, (-1, 9))]));
+        assert!(read(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use serde_json::{json, Map, Value};
+use crate::JobResult;
+
+pub struct JsonLog {
+    file: Mutex<File>,
+}
+
+impl JsonLog {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<JsonLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(JsonLog { file: Mutex::new(file) })
+    }
+
+    /// Appends one JSON object per line; output that isn't valid UTF-8 is converted lossily.
+    pub fn record(&self, job: usize, result: &JobResult, command: &str, values: &[(&str, &str)], output: &[Vec<u8>; 2]) -> io::Result<()> {
+        let (exitval, signal) = result.status.exit_signal();
+        let values: Map<String, Value> = values.iter().map(|(identifier, value)| (identifier.to_string(), json!(value))).collect();
+        let line = json!({
+            "index": job,
+            "values": values,
+            "command": command,
+            "status": result.status.to_string(),
+            "exitval": exitval,
+            "signal": signal,
+            "start": result.start.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64(),
+            "duration": result.runtime.as_secs_f64(),
+            "stdout": String::from_utf8_lossy(&output[0]),
+            "stderr": String::from_utf8_lossy(&output[1]),
+        });
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{}", line)?;
+        file.flush()
+    }
+}
@@ -1,262 +1,2523 @@
-use std::process::Command;
+mod async_exec;
+mod checkpoint;
+mod control;
+mod database;
+mod dates;
+mod joblog;
+mod jsonlog;
+mod mask;
+mod metrics;
+mod modifier;
+mod notify;
+mod otel;
+mod progress;
+mod output;
+mod results;
+mod selection;
+mod shell;
+mod space;
+mod tui;
+mod wordlist;
+
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::borrow::Cow;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use clap::Parser;
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use checkpoint::Checkpoint;
+use database::Database;
+use dates::Dates;
+use joblog::JobLog;
+use jsonlog::JsonLog;
+use mask::Mask;
+use metrics::Metrics;
+use modifier::Modifier;
+use otel::Tracer;
+use output::{JobOutput, Mode, Printer, Stream};
+use results::{Capture, Compression, Results};
+use selection::Selection;
+use shell::Shell;
+use space::Space;
+use wordlist::{Format, Separator, Sort, Streamed, Wordlist};
+use regex::bytes::Regex;
+use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
+use shell_quote::Sh;
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP, SIGTTIN, SIGTTOU, SIGUSR1};
+use signal_hook::iterator::Signals;
 
 fn is_alphanumeric(input: &str) -> bool {
     input.chars().all(|c| c.is_ascii_alphanumeric())
 }
 
-fn execute_command(command: &str, command_idx: usize, no_output: bool) {
-    match Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .output() {
-        Ok(output) => {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if !no_output {
-                    print!("{}", stdout);
-                }
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                if !no_output {
-                    eprint!("{} {}", format!("Error in {}:", command_idx).red().bold(), stderr.red());
-                }
-            }
-        }
-        Err(err) => {
-            eprintln!("{} Failed to execute `{}`: {}", "warning:".yellow().bold(), command, err);
+fn stream_output<R: Read>(mut reader: R, mut write: impl FnMut(&[u8])) {
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => write(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
         }
     }
 }
 
-fn precompute_template(command: &String, index: &String, loaded_wordlist: &Vec<(String, Vec<String>)>) -> Vec<(usize, String)> {
-    let mut template: Vec<(usize, String)> = vec![];
-    let mut tmp = String::new();
-    let mut i = 0;
-    while i < command.len() {
-        let mut found = false;
-        if !index.is_empty() && command[i..].starts_with(index) {
-            template.push((0, tmp)); 
-            tmp = String::new();
-            i += index.len();
-            found = true;
-        }
-        for j in 0..loaded_wordlist.len() {
-            let identifier = &loaded_wordlist[j].0;
-            if command[i..].starts_with(identifier) {
-                template.push((j+1, tmp)); 
-                tmp = String::new();
-                i += identifier.len();
-                found = true;
-                break;
-            }
+fn wait_child(child: &mut Child, timeout: Option<Duration>) -> io::Result<(ExitStatus, bool)> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(|status| (status, false));
+    };
+    let deadline = Instant::now() + timeout;
+    let mut poll = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
         }
-        if !found {
-            tmp.push_str(&command[i..i+1]);
-            i += 1;
+        let now = Instant::now();
+        if now >= deadline {
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            return child.wait().map(|status| (status, true));
         }
+        thread::sleep(poll.min(deadline - now));
+        poll = (poll * 2).min(Duration::from_millis(50));
     }
-    template.push((0, tmp)); 
-    template
 }
-fn gen_command(template: &Vec<(usize, String)>, idx: usize, loaded_wordlist: &Vec<(String, Vec<String>)>, wordlist_lengths: &Vec<usize>) -> String {
-    let mut command = String::new();
-    let idxs = product(idx, &wordlist_lengths);
-    for tvalue in &template[..template.len()-1] {
-        command.push_str(&tvalue.1);
-        if tvalue.0 == 0 {
-            command.push_str(&idx.to_string());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobStatus {
+    Exited(i32),
+    Signaled(i32),
+    TimedOut,
+    Failed,
+}
+
+impl JobStatus {
+    fn from_exit(status: ExitStatus) -> JobStatus {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => JobStatus::Exited(code),
+            (None, Some(signal)) => JobStatus::Signaled(signal),
+            (None, None) => JobStatus::Failed,
         }
-        else {
-            command.push_str(&loaded_wordlist[tvalue.0-1].1[idxs[tvalue.0-1]]);
+    }
+
+    fn success(&self) -> bool {
+        *self == JobStatus::Exited(0)
+    }
+
+    fn exit_signal(&self) -> (i32, i32) {
+        match *self {
+            JobStatus::Exited(code) => (code, 0),
+            JobStatus::Signaled(signal) => (-1, signal),
+            JobStatus::TimedOut => (-1, libc::SIGKILL),
+            JobStatus::Failed => (-1, 0),
         }
     }
-    command.push_str(&template[template.len()-1].1);
-    command
 }
 
-fn read_lines<P>(filename: P) -> io::Result<Vec<String>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    let lines: Vec<String> = io::BufReader::new(file).lines().filter_map(Result::ok).collect();
-    Ok(lines)
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JobStatus::Exited(code) => write!(f, "exit code {}", code),
+            JobStatus::Signaled(signal) => write!(f, "signal {}", signal),
+            JobStatus::TimedOut => write!(f, "timed out"),
+            JobStatus::Failed => write!(f, "failed to start"),
+        }
+    }
 }
 
-fn product(nth: usize, sizes: &Vec<usize>) -> Vec<usize> {
-    let mut out = vec![];
-    let mut nth = nth;
-    for s in sizes {
-        out.push(nth % s);
-        nth /= s;
-    }
-    return out;
+struct JobResult {
+    status: JobStatus,
+    start: SystemTime,
+    runtime: Duration,
 }
 
-#[derive(Parser, Debug)]
-#[command(name = "parel", version = env!("CARGO_PKG_VERSION"), about = "Parallization CLI tool", disable_version_flag = true)]
-struct Cli {
+struct RunningJob {
+    pid: u32,
     command: String,
-    #[arg(short, long, default_value_t=10, help="Number of threads")]
-    threads: usize,
-    #[arg(long, default_value=None, help="Show nth command that will be executed (0 indexed)")]
-    show: Option<usize>,
-    #[arg(short, long, help="Identifier for index of running job (same number as in --show {})")]
-    index: Option<String>,
-    #[arg(short, long, help="A file and an identifier used in command [example: abc.txt:foo]")]
-    file: Vec<String>,
-    #[arg(short, long, help="Don't show command stdout or stderr")]
-    silent: bool,
-    #[arg(short, long, help="Enable progress bar")]
-    progress: bool,
-    #[arg(long, action = clap::builder::ArgAction::Version)]
-    version: (),
+    started: Instant,
 }
 
-fn main() {
-    let args = Cli::parse();
-    let command = args.command;
+struct State {
+    next_job: AtomicUsize,
+    failed: AtomicUsize,
+    halted: AtomicBool,
+    interrupted: AtomicI32,
+    completed: AtomicUsize,
+    skipped: AtomicUsize,
+    retried: AtomicUsize,
+    cancelled: AtomicUsize,
+    threads: AtomicUsize,
+    paused: AtomicBool,
+    exhausted: AtomicBool,
+    running: Mutex<HashMap<usize, RunningJob>>,
+    failures: Mutex<VecDeque<(usize, JobStatus, String)>>,
+    started: Instant,
+    kill_signal: AtomicI32,
+    /// Whether in-flight jobs are tracked for --checkpoint, which costs a lock per job.
+    checkpointing: bool,
+    in_flight: Mutex<BTreeMap<usize, usize>>,
+    requeued: Mutex<Vec<(usize, usize)>>,
+    dispatched: AtomicUsize,
+}
 
-    let index = if let Some(index) = args.index {
-        if !is_alphanumeric(&index) {
-            eprintln!("{} Identifier {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), index);
-            std::process::exit(1);
+impl State {
+    fn new(threads: usize, checkpointing: bool) -> State {
+        State {
+            next_job: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            halted: AtomicBool::new(false),
+            interrupted: AtomicI32::new(0),
+            completed: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            retried: AtomicUsize::new(0),
+            cancelled: AtomicUsize::new(0),
+            threads: AtomicUsize::new(threads),
+            paused: AtomicBool::new(false),
+            exhausted: AtomicBool::new(false),
+            running: Mutex::new(HashMap::new()),
+            failures: Mutex::new(VecDeque::new()),
+            started: Instant::now(),
+            kill_signal: AtomicI32::new(0),
+            checkpointing,
+            in_flight: Mutex::new(BTreeMap::new()),
+            requeued: Mutex::new(vec![]),
+            dispatched: AtomicUsize::new(0),
         }
-        index
     }
-    else {
-        "".to_string()
-    };
 
-    let mut files: Vec<(String, String)> = vec![];
+    fn restore(&self, checkpoint: Checkpoint) {
+        self.next_job.store(checkpoint.next, Ordering::SeqCst);
+        self.completed.store(checkpoint.completed, Ordering::SeqCst);
+        self.failed.store(checkpoint.failed, Ordering::SeqCst);
+        *self.requeued.lock().unwrap() = checkpoint.pending;
+    }
 
-    for line in args.file {
-        let mut identifier: String = "".to_string();
-        let mut path: String = "".to_string();
-        for j in (0..line.len()).rev() {
-            if line[j..j+1] == *":" {
-                identifier = line[j+1..].to_string();
-                path = line[..j].to_string();
+    fn checkpoint(&self, command: &str, selection: &Selection, total: usize) -> Checkpoint {
+        let in_flight = self.in_flight.lock().unwrap();
+        let next = self.next_job.load(Ordering::SeqCst);
+        let mut pending: Vec<(usize, usize)> = in_flight.iter().map(|(&job, &attempt)| (job, attempt)).collect();
+        pending.extend(self.requeued.lock().unwrap().iter());
+        Checkpoint {
+            command: format!("{:?}", command),
+            selection: format!("{:?}", selection),
+            total,
+            next,
+            completed: self.completed.load(Ordering::SeqCst),
+            failed: self.failed.load(Ordering::SeqCst),
+            pending,
+        }
+    }
+
+    /// Claims up to `size` jobs, each with its dispatch sequence number, job index and the attempt to continue from.
+    fn claim(&self, only: &Option<Vec<usize>>, skip: &HashSet<usize>, selection: &Selection, size: usize) -> Option<Vec<(usize, usize, usize)>> {
+        if !self.checkpointing {
+            let jobs = self.next(only, skip, selection, size)?;
+            let seq = self.dispatched.fetch_add(jobs.len(), Ordering::SeqCst);
+            return Some(jobs.into_iter().enumerate().map(|(i, job)| (seq + i, job, 0)).collect());
+        }
+        // Advancing under the in_flight lock keeps `next` and `pending` of a checkpoint consistent.
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let jobs = match self.requeued.lock().unwrap().pop() {
+            Some(requeued) => vec![requeued],
+            None => self.next(only, skip, selection, size)?.into_iter().map(|job| (job, 0)).collect(),
+        };
+        in_flight.extend(jobs.iter().copied());
+        let seq = self.dispatched.fetch_add(jobs.len(), Ordering::SeqCst);
+        Some(jobs.into_iter().enumerate().map(|(i, (job, attempt))| (seq + i, job, attempt)).collect())
+    }
+
+    /// Takes the next `size` positions, leaving out skipped jobs. The counter keeps growing past the end, which is harmless.
+    fn next(&self, only: &Option<Vec<usize>>, skip: &HashSet<usize>, selection: &Selection, size: usize) -> Option<Vec<usize>> {
+        let end = only.as_ref().map_or(selection.len(), Vec::len);
+        loop {
+            let start = self.next_job.fetch_add(size, Ordering::SeqCst);
+            if start >= end {
+                return None;
+            }
+            let jobs: Vec<usize> = (start..end.min(start.saturating_add(size)))
+                .map(|next| only.as_ref().map_or_else(|| selection.get(next).unwrap(), |only| only[next]))
+                .filter(|job| !skip.contains(job))
+                .collect();
+            if !jobs.is_empty() {
+                return Some(jobs);
             }
         }
-        if identifier.is_empty() {
-            eprintln!("{} Missing identifier, example: '-f {}:foo'", "error:".red().bold(), line);
-            std::process::exit(1);
+    }
+
+    fn retrying(&self, job: usize, attempt: usize) {
+        if self.checkpointing {
+            self.in_flight.lock().unwrap().insert(job, attempt);
         }
-        if !is_alphanumeric(&identifier) {
-            eprintln!("{} Identifier {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), &identifier);
-            std::process::exit(1);
+        if attempt == 1 {
+            self.retried.fetch_add(1, Ordering::SeqCst);
         }
-        if files.iter().any(|(f, _)| f == &identifier || *f == index) {
-            eprintln!("{} identifier '{}' aready exists", "error:".red().bold(), identifier);
-            std::process::exit(1);
+    }
+
+    fn finish(&self, job: usize, success: bool) -> usize {
+        let mut in_flight = self.checkpointing.then(|| self.in_flight.lock().unwrap());
+        if let Some(ref mut in_flight) = in_flight {
+            in_flight.remove(&job);
         }
-        if !Path::new(&path).exists() {
-            eprintln!("{} file '{}' does not exist", "error:".red().bold(), path);
-            std::process::exit(1);
+        self.completed.fetch_add(1, Ordering::SeqCst);
+        if success {
+            self.failed.load(Ordering::SeqCst)
+        }
+        else {
+            self.failed.fetch_add(1, Ordering::SeqCst) + 1
         }
+    }
 
-        if !command.contains(&index) {
-            eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), index);
-            std::process::exit(1);
+    /// Remembers the last `MAX_FAILURES` failed jobs for --tui.
+    fn record_failure(&self, job: usize, status: JobStatus, command: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() == MAX_FAILURES {
+            failures.pop_front();
         }
-        if !command.contains(&identifier) {
-            eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), identifier);
-            std::process::exit(1);
+        failures.push_back((job, status, command.to_string()));
+    }
+
+    fn skip(&self, job: usize) {
+        let mut in_flight = self.checkpointing.then(|| self.in_flight.lock().unwrap());
+        if let Some(ref mut in_flight) = in_flight {
+            in_flight.remove(&job);
+        }
+        self.skipped.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Counts a job killed because another one matched --until-match, which neither succeeded nor failed.
+    fn cancel(&self, job: usize) {
+        let mut in_flight = self.checkpointing.then(|| self.in_flight.lock().unwrap());
+        if let Some(ref mut in_flight) = in_flight {
+            in_flight.remove(&job);
         }
-        files.push((identifier.clone(), path));
+        self.cancelled.fetch_add(1, Ordering::SeqCst);
     }
 
-    let mut total_words = 1;
-    let mut loaded_wordlist: Vec<(String, Vec<String>)> = vec![];
+    fn stopping(&self) -> bool {
+        self.halted.load(Ordering::SeqCst) || self.interrupted.load(Ordering::SeqCst) != 0
+    }
 
-    let mut wordlist_lengths: Vec<usize> = vec![];
-    for (identifier, path) in files {
-        let lines = read_lines(&path).expect(&format!("{} Could not read {}", "error:".red().bold(), &path));
-       
+    async fn wait_while_paused(&self, asynchronous: bool) {
+        while self.paused.load(Ordering::SeqCst) && !self.stopping() {
+            async_exec::sleep(asynchronous, PAUSE_INTERVAL).await;
+        }
+    }
 
-        total_words *= lines.len();
-        wordlist_lengths.push(lines.len());
+    /// Stops dispatching and kills every running job, like `--halt now`.
+    fn abort(&self) {
+        if !self.halted.swap(true, Ordering::SeqCst) {
+            self.kill_running(libc::SIGKILL);
+        }
+    }
 
+    fn register(&self, idx: usize, pid: u32, command: &str) {
+        let mut running = self.running.lock().unwrap();
+        let signal = self.kill_signal.load(Ordering::SeqCst);
+        if signal != 0 {
+            unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+        }
+        running.insert(idx, RunningJob { pid, command: command.to_string(), started: Instant::now() });
+    }
 
-        loaded_wordlist.push((identifier, lines));
+    fn unregister(&self, idx: usize) {
+        self.running.lock().unwrap().remove(&idx);
     }
 
+    /// Sends `signal` to the process group of every running job, and of every job started from now on.
+    fn kill_running(&self, signal: i32) -> usize {
+        self.kill_running_except(signal, None)
+    }
 
-    let template = precompute_template(&command, &index, &loaded_wordlist);
+    fn kill_running_except(&self, signal: i32, except: Option<usize>) -> usize {
+        let running = self.running.lock().unwrap();
+        self.kill_signal.store(signal, Ordering::SeqCst);
+        let mut killed = 0;
+        for (&job, running) in running.iter() {
+            if Some(job) != except {
+                unsafe { libc::kill(-(running.pid as libc::pid_t), signal) };
+                killed += 1;
+            }
+        }
+        killed
+    }
 
-    if let Some(show) = args.show {
-        if show >= total_words {
-            eprintln!("{} show parameter {} cannot be more than {}", "error:".red().bold(), show, total_words);
-            std::process::exit(1);
+    /// Estimated time until all `total` jobs are done, based on the completion rate so far.
+    fn eta(&self, total: usize) -> Option<Duration> {
+        let completed = self.completed.load(Ordering::SeqCst) + self.skipped.load(Ordering::SeqCst);
+        if completed == 0 {
+            return None;
         }
-        let command = gen_command(&template, show, &loaded_wordlist, &wordlist_lengths);
-        println!("{}", command);
-        std::process::exit(0);
+        let remaining = total.saturating_sub(completed);
+        Duration::try_from_secs_f64(self.started.elapsed().as_secs_f64() / completed as f64 * remaining as f64).ok()
+    }
+
+    /// One line of --progress output for when stderr isn't a terminal.
+    fn print_progress(&self, total: usize) {
+        let completed = self.completed.load(Ordering::SeqCst) + self.skipped.load(Ordering::SeqCst);
+        let rate = completed as f64 / self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let eta = match self.eta(total) {
+            Some(eta) => indicatif::HumanDuration(eta).to_string(),
+            None => "unknown".to_string(),
+        };
+        eprintln!("[{}] {}/{} jobs ({} failed), {} running, {:.1} jobs/s, ETA {}", timestamp(), completed, total,
+            self.failed.load(Ordering::SeqCst), self.running.lock().unwrap().len(), rate, eta);
     }
-    let progress_bar = if args.progress {
-        let pb = ProgressBar::new(total_words as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({percent}%)")
-                .unwrap()
-                .progress_chars("#>-")
-        );
-        pb.set_position(0);
-        Some(Arc::new(Mutex::new(pb)))
+
+    fn print_status(&self, total: usize) {
+        let running = self.running.lock().unwrap();
+        let eta = match self.eta(total) {
+            Some(eta) => format!("ETA {}", indicatif::HumanDuration(eta)),
+            None => "ETA unknown".to_string(),
+        };
+        eprintln!("{} {}/{} jobs done ({} failed), {} running, {}", "info:".cyan().bold(),
+            self.completed.load(Ordering::SeqCst), total, self.failed.load(Ordering::SeqCst), running.len(), eta);
+        let mut jobs: Vec<(&usize, &RunningJob)> = running.iter().collect();
+        jobs.sort_unstable_by_key(|&(&job, _)| job);
+        for (job, running) in jobs {
+            eprintln!("  {} {:.1}s {}", format!("[{}]", job).cyan().bold(), running.started.elapsed().as_secs_f64(), running.command);
+        }
     }
-    else { None };
+}
 
-    let next_job = Arc::new(Mutex::new(0));
+#[derive(Debug, Clone, Copy)]
+struct HaltPolicy {
+    now: bool,
+    fail: usize,
+}
 
-    let wordlist_lengths = Arc::new(wordlist_lengths);
-    let loaded_wordlist = Arc::new(loaded_wordlist);
-    let template = Arc::new(template);
+fn parse_halt(value: &str) -> Result<HaltPolicy, String> {
+    let mut now = false;
+    let mut fail = None;
+    for part in value.split(',') {
+        match part {
+            "now" => now = true,
+            "soon" => now = false,
+            _ => {
+                let count = part.strip_prefix("fail=")
+                    .ok_or(format!("unknown halt condition '{}', expected [now,|soon,]fail=N", part))?;
+                let count: usize = count.parse().map_err(|_| format!("'{}' is not a number", count))?;
+                if count == 0 {
+                    return Err("fail=N must be at least 1".to_string());
+                }
+                fail = Some(count);
+            }
+        }
+    }
+    let fail = fail.ok_or("missing fail=N condition".to_string())?;
+    Ok(HaltPolicy { now, fail })
+}
 
+#[derive(Debug, Clone, Copy)]
+struct Shard {
+    index: usize,
+    count: usize,
+}
 
-    let mut threads = vec![];
-    for _ in 0..args.threads {
-        let wordlist_lengths = wordlist_lengths.clone();
-        let loaded_wordlist = loaded_wordlist.clone();
-        let template = template.clone();
-        let next_job = next_job.clone();
-        let progress_bar = progress_bar.clone();
-    
-        threads.push(thread::spawn(move || {
-            loop {
-                let job = {
-                    let mut job = next_job.lock().unwrap();
-                    let out = *job;
-                    if out > total_words {
-                        break;
+fn parse_shard(value: &str) -> Result<Shard, String> {
+    let (index, count) = value.split_once('/').ok_or(format!("'{}' is not of the form i/n", value))?;
+    let index: usize = index.parse().map_err(|_| format!("'{}' is not a number", index))?;
+    let count: usize = count.parse().map_err(|_| format!("'{}' is not a number", count))?;
+    if index == 0 || index > count {
+        return Err(format!("shard {} has to be between 1 and {}", index, count));
+    }
+    Ok(Shard { index, count })
+}
+
+/// Numbers from `start` to `end` for --range, both included.
+#[derive(Debug, Clone)]
+struct NumberRange {
+    start: i64,
+    step: i64,
+    len: usize,
+    identifier: String,
+}
+
+fn parse_range(value: &str) -> Result<NumberRange, String> {
+    let (range, identifier) = value.rsplit_once(':').filter(|(_, identifier)| !identifier.is_empty())
+        .ok_or(format!("missing identifier, example: '{}:foo'", value.trim_end_matches(':')))?;
+    let (bounds, step) = range.split_once('/').unwrap_or((range, "1"));
+    // Skips the sign of a negative start.
+    let split = bounds.get(1..).and_then(|rest| rest.find('-')).map(|i| i + 1).ok_or(format!("'{}' is not of the form start-end", bounds))?;
+    let (start, end) = (&bounds[..split], &bounds[split + 1..]);
+    let start: i64 = start.parse().map_err(|_| format!("'{}' is not a number", start))?;
+    let end: i64 = end.parse().map_err(|_| format!("'{}' is not a number", end))?;
+    let step: i64 = step.parse().map_err(|_| format!("'{}' is not a number", step))?;
+    if step <= 0 {
+        return Err("the step has to be at least 1".to_string());
+    }
+    let len = usize::try_from((end as i128 - start as i128).unsigned_abs() / step as u128 + 1).map_err(|_| format!("{} has too many numbers", range))?;
+    Ok(NumberRange { start, step: if end < start { -step } else { step }, len, identifier: identifier.to_string() })
+}
+
+/// Parses `start..end[/unit]:identifier[:format]` for --dates.
+fn parse_dates(value: &str) -> Result<(Dates, String), String> {
+    let (range, rest) = value.split_once(':').ok_or(format!("missing identifier, example: '{}:foo'", value))?;
+    let (identifier, format) = match rest.split_once(':') {
+        Some((identifier, format)) => (identifier, Some(format)),
+        None => (rest, None),
+    };
+    if identifier.is_empty() {
+        return Err(format!("missing identifier, example: '{}:foo'", range));
+    }
+    let (range, unit) = match range.split_once('/') {
+        Some((range, unit)) => (range, Some(unit)),
+        None => (range, None),
+    };
+    Ok((Dates::parse(range, unit, format)?, identifier.to_string()))
+}
+
+fn parse_mask(value: &str) -> Result<(Mask, String), String> {
+    let (mask, identifier) = value.rsplit_once(':').filter(|(_, identifier)| !identifier.is_empty())
+        .ok_or(format!("missing identifier, example: '{}:foo'", value.trim_end_matches(':')))?;
+    Ok((Mask::parse(mask)?, identifier.to_string()))
+}
+
+fn parse_delimiter(value: &str) -> Result<Separator, String> {
+    if value.is_empty() {
+        return Ok(Separator::Paragraph);
+    }
+    let mut bytes = vec![];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('0') => bytes.push(0),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| format!("'\\x{}' is not a byte in hex", hex))?);
+            }
+            Some(c) => return Err(format!("unknown escape '\\{}'", c)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+    Ok(Separator::Bytes(bytes))
+}
+
+/// A worker thread, or a task on the runtime with --async.
+enum WorkerHandle {
+    Thread(thread::JoinHandle<()>),
+    Task(tokio::task::JoinHandle<()>),
+}
+
+impl WorkerHandle {
+    fn is_finished(&self) -> bool {
+        match self {
+            WorkerHandle::Thread(thread) => thread.is_finished(),
+            WorkerHandle::Task(task) => task.is_finished(),
+        }
+    }
+
+    /// Tasks are only joined once finished, which leaves nothing to wait for.
+    fn join(self) {
+        if let WorkerHandle::Thread(thread) = self {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Keeps `state.threads` workers running until no jobs are left, spawning new ones when the count is raised.
+fn run_workers(state: &State, spawn: impl Fn(usize) -> WorkerHandle) {
+    let mut workers: Vec<Option<WorkerHandle>> = vec![];
+    loop {
+        if !state.exhausted.load(Ordering::SeqCst) && !state.stopping() {
+            let threads = state.threads.load(Ordering::SeqCst);
+            if workers.len() < threads {
+                workers.resize_with(threads, || None);
+            }
+            for (id, worker) in workers.iter_mut().enumerate().take(threads) {
+                if worker.as_ref().is_none_or(|worker| worker.is_finished()) {
+                    if let Some(worker) = worker.take() {
+                        worker.join();
                     }
-                    else {
-                        *job += 1;
-                        out
+                    *worker = Some(spawn(id));
+                }
+            }
+        }
+        else if workers.iter().flatten().all(|worker| worker.is_finished()) {
+            break;
+        }
+        thread::sleep(SUPERVISOR_INTERVAL);
+    }
+    for worker in workers.into_iter().flatten() {
+        worker.join();
+    }
+}
+
+fn handle_signals(state: Arc<State>, total: usize) {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP, SIGUSR1, SIGTTIN, SIGTTOU, SIGTSTP, SIGCONT]).expect("failed to install signal handler");
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            match signal {
+                SIGUSR1 => {
+                    state.print_status(total);
+                    continue;
+                }
+                SIGTTIN | SIGTTOU => {
+                    let threads = state.threads.load(Ordering::SeqCst);
+                    let threads = if signal == SIGTTIN { threads + 1 } else { threads.saturating_sub(1).max(1) };
+                    state.threads.store(threads, Ordering::SeqCst);
+                    eprintln!("{} Running with {} threads", "info:".cyan().bold(), threads);
+                    continue;
+                }
+                SIGTSTP | SIGCONT => {
+                    let paused = signal == SIGTSTP;
+                    if state.paused.swap(paused, Ordering::SeqCst) != paused {
+                        let running = state.running.lock().unwrap().len();
+                        if paused {
+                            eprintln!("{} Paused, {} running jobs will finish (send SIGCONT to resume)", "info:".cyan().bold(), running);
+                        }
+                        else {
+                            eprintln!("{} Resumed", "info:".cyan().bold());
+                        }
                     }
-                };
-                let command = gen_command(&template, job, &loaded_wordlist, &wordlist_lengths);
-                execute_command(&command, job, args.silent);
+                    continue;
+                }
+                _ => {}
+            }
+            let first = state.interrupted.compare_exchange(0, signal, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+            if signal != SIGINT {
+                let forwarded = state.kill_running(signal);
+                eprintln!("{} Received {}, forwarded to {} running jobs", "warning:".yellow().bold(), signal_name(signal), forwarded);
+            }
+            else if first {
+                let running = state.running.lock().unwrap().len();
+                eprintln!("{} Interrupted, waiting for {} running jobs to finish (press Ctrl-C again to kill them)", "warning:".yellow().bold(), running);
+            }
+            else {
+                let killed = state.kill_running(libc::SIGKILL);
+                eprintln!("{} Killed {} running jobs", "error:".red().bold(), killed);
+                std::process::exit(128 + SIGINT);
+            }
+        }
+    });
+}
 
-                if let Some(ref pb) = progress_bar {
-                    pb.lock().unwrap().inc(1);
+fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        SIGINT => "SIGINT",
+        SIGTERM => "SIGTERM",
+        SIGHUP => "SIGHUP",
+        _ => "signal",
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ExecOptions {
+    silent: bool,
+    no_stdout: bool,
+    no_stderr: bool,
+    /// Output that isn't printed still has to be read to look for --until-match.
+    until_match: bool,
+    timeout: Option<Duration>,
+}
+
+/// Saves and prints the output of a job, and stops the other jobs once it matched --until-match.
+fn output_writer<'a>(command_idx: usize, options: &ExecOptions, output: &'a JobOutput<'a>, capture: &'a Capture, state: &'a State) -> impl Fn(Stream, Option<&[u8]>) + Sync + 'a {
+    let print_stdout = !options.silent && !options.no_stdout;
+    let print_stderr = !options.silent && !options.no_stderr;
+    move |stream: Stream, chunk: Option<&[u8]>| {
+        if let Some(chunk) = chunk {
+            if let Err(err) = capture.write(stream, chunk) {
+                eprintln!("{} Could not save output of {}: {}", "warning:".yellow().bold(), command_idx, err);
+            }
+        }
+        if (stream == Stream::Stdout && !print_stdout) || (stream == Stream::Stderr && !print_stderr) {
+            output.watch(stream, chunk);
+        }
+        else {
+            match chunk {
+                Some(chunk) => output.write(stream, chunk),
+                None => output.end(stream),
+            }
+        }
+        if output.won() && !state.halted.swap(true, Ordering::SeqCst) {
+            state.kill_running_except(libc::SIGKILL, Some(command_idx));
+        }
+    }
+}
+
+/// How a job's command is started.
+enum Runner<'a> {
+    Sh,
+    /// The worker's --persistent-shell, started on first use and replaced once it died.
+    Persistent(&'a mut Option<Shell>),
+    /// The substituted arguments of --no-shell.
+    Exec(&'a [String]),
+}
+
+fn execute_command(command: &str, command_idx: usize, options: ExecOptions, output: &JobOutput, capture: &Capture, state: &State, runner: Runner) -> JobResult {
+    let start = SystemTime::now();
+    let started = Instant::now();
+    let timeout = options.timeout;
+    let write = output_writer(command_idx, &options, output, capture, state);
+
+    if let Runner::Persistent(shell) = runner {
+        if shell.as_ref().is_none_or(Shell::dead) {
+            *shell = match Shell::spawn() {
+                Ok(shell) => Some(shell),
+                Err(err) => {
+                    eprintln!("{} Failed to start a shell for `{}`: {}", "warning:".yellow().bold(), command, err);
+                    return JobResult { status: JobStatus::Failed, start, runtime: started.elapsed() };
                 }
+            };
+        }
+        let shell = shell.as_mut().unwrap();
+        state.register(command_idx, shell.id(), command);
+        let result = shell.run(command, timeout, write);
+        state.unregister(command_idx);
+        return job_result(command, command_idx, &options, output, result, start, started.elapsed());
+    }
+
+    let stdio = |discard: bool| if discard && !capture.active() && !options.until_match { Stdio::null() } else { Stdio::piped() };
+    let mut program = match runner {
+        Runner::Exec(argv) => {
+            let mut program = Command::new(&argv[0]);
+            program.args(&argv[1..]);
+            program
+        }
+        _ => {
+            let mut program = Command::new("sh");
+            program.arg("-c").arg(command);
+            program
+        }
+    };
+    let mut child = match program
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(stdio(options.silent || options.no_stdout))
+        .stderr(stdio(options.silent || options.no_stderr))
+        .spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("{} Failed to execute `{}`: {}", "warning:".yellow().bold(), command, err);
+            return JobResult { status: JobStatus::Failed, start, runtime: started.elapsed() };
+        }
+    };
+
+    state.register(command_idx, child.id(), command);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let result = thread::scope(|s| {
+        if let Some(stderr) = stderr {
+            s.spawn(|| {
+                stream_output(stderr, |chunk| write(Stream::Stderr, Some(chunk)));
+                write(Stream::Stderr, None);
+            });
+        }
+        if let Some(stdout) = stdout {
+            s.spawn(|| {
+                stream_output(stdout, |chunk| write(Stream::Stdout, Some(chunk)));
+                write(Stream::Stdout, None);
+            });
+        }
+        wait_child(&mut child, timeout)
+    });
+    state.unregister(command_idx);
+    job_result(command, command_idx, &options, output, result, start, started.elapsed())
+}
+
+fn job_result(command: &str, command_idx: usize, options: &ExecOptions, output: &JobOutput, result: io::Result<(ExitStatus, bool)>, start: SystemTime, runtime: Duration) -> JobResult {
+    let timeout = options.timeout;
+    let status = match result {
+        Ok((_, true)) => {
+            if !options.silent {
+                let timeout = timeout.unwrap_or_default().as_secs_f64();
+                output.message(format!("{} {}", format!("Error in {}:", command_idx).red().bold(), format!("timed out after {}s", timeout).red()));
+            }
+            JobStatus::TimedOut
+        }
+        Ok((status, false)) => {
+            if !status.success() && !options.silent {
+                output.message(format!("{} {}", format!("Error in {}:", command_idx).red().bold(), status.to_string().red()));
             }
-        }));
+            JobStatus::from_exit(status)
+        }
+        Err(err) => {
+            eprintln!("{} Failed to wait for `{}`: {}", "warning:".yellow().bold(), command, err);
+            JobStatus::Failed
+        }
+    };
+    JobResult { status, start, runtime }
+}
+
+/// User plus system time of all finished jobs, including their own children.
+fn children_cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        return Duration::ZERO;
     }
+    let timeval = |tv: libc::timeval| Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+    timeval(usage.ru_utime) + timeval(usage.ru_stime)
+}
 
-    for thread in threads {
-        let _ = thread.join();
+/// `sampled_from` is the number of jobs a --sample was taken from.
+fn print_summary(state: &State, total: usize, sampled_from: Option<usize>, wall: Duration) {
+    let completed = state.completed.load(Ordering::SeqCst);
+    let failed = state.failed.load(Ordering::SeqCst);
+    let failed_text = format!("{} failed", failed);
+    let rate = completed as f64 / wall.as_secs_f64().max(f64::EPSILON);
+    let sample = sampled_from.map_or(String::new(), |from| format!(" (a random sample of {})", from));
+    let cancelled = match state.cancelled.load(Ordering::SeqCst) {
+        0 => String::new(),
+        cancelled => format!(", {} cancelled", cancelled),
+    };
+    eprintln!("{} {} jobs{}: {} succeeded, {}, {} retried, {} skipped{} in {:.2}s, {:.1} jobs/s (CPU time {:.2}s)", "info:".cyan().bold(),
+        total, sample, completed - failed, if failed > 0 { failed_text.red() } else { failed_text.normal() }, state.retried.load(Ordering::SeqCst),
+        state.skipped.load(Ordering::SeqCst), cancelled, wall.as_secs_f64(), rate, children_cpu_time().as_secs_f64());
+}
+
+/// A title and one line describing how the run ended, for notifications.
+fn outcome(state: &State, total: usize) -> (String, String) {
+    let title = if state.interrupted.load(Ordering::SeqCst) != 0 {
+        "parel was interrupted"
     }
-    if let Some(ref pb) = progress_bar {
-        pb.lock().unwrap().finish();
+    else if state.halted.load(Ordering::SeqCst) {
+        "parel stopped early"
+    }
+    else {
+        "parel finished"
+    };
+    let completed = state.completed.load(Ordering::SeqCst);
+    let failed = state.failed.load(Ordering::SeqCst);
+    (title.to_string(), format!("{} of {} jobs done, {} succeeded, {} failed", completed, total, completed - failed, failed))
+}
+
+/// The --webhook body; `text` is what Slack and Matrix hooks display.
+fn webhook_payload(state: &State, total: usize, event: &str) -> serde_json::Value {
+    let text = if event == "failures" {
+        format!("parel: {} jobs failed so far, {} of {} jobs done", state.failed.load(Ordering::SeqCst), state.completed.load(Ordering::SeqCst), total)
+    }
+    else {
+        let (title, body) = outcome(state, total);
+        format!("{}: {}", title, body)
+    };
+    let mut payload = progress::status(state, total);
+    payload["event"] = serde_json::json!(event);
+    payload["text"] = serde_json::json!(text);
+    payload
+}
+
+/// Nearest-rank percentile of the sorted `durations`.
+fn percentile(durations: &[(Duration, usize)], p: f64) -> Duration {
+    let rank = (p * durations.len() as f64).ceil() as usize;
+    durations[rank.clamp(1, durations.len()) - 1].0
+}
+
+fn print_stats(durations: &mut [(Duration, usize)], slowest: usize, command: impl Fn(usize) -> String) {
+    if durations.is_empty() {
+        return;
+    }
+    durations.sort_unstable();
+    eprintln!("{} Job durations: min {:.3}s, median {:.3}s, p95 {:.3}s, max {:.3}s", "info:".cyan().bold(),
+        durations[0].0.as_secs_f64(), percentile(durations, 0.5).as_secs_f64(),
+        percentile(durations, 0.95).as_secs_f64(), durations[durations.len() - 1].0.as_secs_f64());
+    for &(duration, job) in durations.iter().rev().take(slowest) {
+        eprintln!("  {:>9.3}s  {} {}", duration.as_secs_f64(), format!("[{}]", job).cyan().bold(), command(job));
+    }
+}
+
+fn confirm(tty: &Mutex<io::BufReader<File>>, command: &str) -> bool {
+    let mut tty = tty.lock().unwrap();
+    eprint!("{} ?...", command);
+    let mut answer = String::new();
+    if tty.read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+fn should_retry(status: JobStatus, retry_on_exit: &[i32]) -> bool {
+    if status.success() {
+        return false;
+    }
+    match status {
+        JobStatus::Exited(code) => retry_on_exit.is_empty() || retry_on_exit.contains(&code),
+        _ => retry_on_exit.is_empty(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ShowIndex {
+    Nth(usize),
+    Last,
+}
+
+impl ShowIndex {
+    fn resolve(self, total: usize) -> usize {
+        match self {
+            ShowIndex::Nth(nth) => nth,
+            ShowIndex::Last => total.saturating_sub(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ShowSpec(Vec<(ShowIndex, ShowIndex)>);
+
+fn parse_show(value: &str) -> Result<ShowSpec, String> {
+    let index = |value: &str| match value {
+        "last" => Ok(ShowIndex::Last),
+        _ => value.parse().map(ShowIndex::Nth).map_err(|_| format!("'{}' is not an index", value)),
+    };
+    let mut ranges = vec![];
+    for part in value.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => ranges.push((index(start)?, index(end)?)),
+            None => ranges.push((index(part)?, index(part)?)),
+        }
+    }
+    Ok(ShowSpec(ranges))
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|err| err.to_string())
+}
+
+fn parse_backoff(value: &str) -> Result<f64, String> {
+    let factor: f64 = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+    if !factor.is_finite() || factor < 1.0 {
+        return Err(format!("'{}' must be at least 1", value));
+    }
+    Ok(factor)
+}
+
+fn retry_delay(delay: Duration, backoff: f64, attempt: usize) -> Duration {
+    let factor = backoff.powi(attempt.saturating_sub(1).min(i32::MAX as usize) as i32);
+    Duration::try_from_secs_f64(delay.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let secs: f64 = value.parse().map_err(|_| format!("'{}' is not a number of seconds", value))?;
+    if !secs.is_finite() || secs <= 0.0 {
+        return Err(format!("'{}' must be a positive number of seconds", value));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ProgressInterval {
+    Time(Duration),
+    Jobs(usize),
+}
+
+/// Accepts seconds like `10` or `2.5s`, or a number of finished jobs like `500j`.
+fn parse_progress_interval(value: &str) -> Result<ProgressInterval, String> {
+    if let Some(jobs) = value.strip_suffix('j') {
+        return match jobs.parse() {
+            Ok(jobs) if jobs > 0 => Ok(ProgressInterval::Jobs(jobs)),
+            _ => Err(format!("'{}' must be a positive number of jobs", value)),
+        };
+    }
+    parse_seconds(value.strip_suffix('s').unwrap_or(value)).map(ProgressInterval::Time)
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let mut buf = [0u8; 32];
+    let len = unsafe {
+        libc::localtime_r(&now, &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), c"%Y-%m-%d %H:%M:%S".as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+/// Drops the lines of the wordlist, or rows of the columns, under `identifier` that came before for --unique.
+fn drop_duplicates(identifier: &str, columns: Vec<Wordlist>) -> Vec<Wordlist> {
+    let (columns, removed) = wordlist::unique(columns);
+    if removed > 0 {
+        eprintln!("{} Removed {} duplicate lines of {}", "info:".cyan().bold(), removed, identifier);
+    }
+    columns
+}
+
+/// Tells how many entries of `source` weren't valid UTF-8, so they aren't lost without notice.
+fn warn_invalid(format: &Format, source: &str) {
+    let invalid = format.take_invalid();
+    if invalid == 0 {
+        return;
+    }
+    if format.lossy {
+        eprintln!("{} {} lines of {} aren't valid UTF-8, the invalid bytes were replaced", "warning:".yellow().bold(), invalid, source);
+    }
+    else {
+        eprintln!("{} Left out {} lines of {} that aren't valid UTF-8, --lossy keeps them", "warning:".yellow().bold(), invalid, source);
+    }
+}
+
+/// Exits unless `identifier` can name a wordlist: alphanumeric, not taken yet and in the command.
+fn check_identifier(identifier: &str, index: &str, command: &str, braces: bool, loaded_wordlist: &[(String, Arc<Wordlist>)]) {
+    if identifier.is_empty() || !is_alphanumeric(identifier) {
+        eprintln!("{} Identifier {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), identifier);
+        std::process::exit(1);
+    }
+    if identifier == index || loaded_wordlist.iter().any(|(other, _)| other == identifier) {
+        eprintln!("{} identifier '{}' aready exists", "error:".red().bold(), identifier);
+        std::process::exit(1);
+    }
+    if !in_command(command, identifier, braces) {
+        eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), identifier);
+        std::process::exit(1);
+    }
+}
+
+/// Exits if an identifier starts with another one, then the shorter one can't be followed by what makes up the longer.
+/// `numbered` wordlists are the `N1`, `N2`... of --combinations and --permutations, which can't clash as their number
+/// is always written out.
+fn check_prefixes(index: &str, loaded_wordlist: &[(String, Arc<Wordlist>)], numbered: bool) {
+    let identifiers: Vec<(&str, bool)> = std::iter::once((index, false)).filter(|(index, _)| !index.is_empty())
+        .chain(loaded_wordlist.iter().map(|(identifier, _)| (identifier.as_str(), numbered))).collect();
+    for (i, &(identifier, generated)) in identifiers.iter().enumerate() {
+        for (j, &(other, other_generated)) in identifiers.iter().enumerate() {
+            if i != j && !(generated && other_generated) && other.starts_with(identifier) {
+                eprintln!("{} Identifier '{}' is a prefix of '{}', rename one of them or use --braces", "error:".red().bold(), identifier, other);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// What is between the braces of every `{...}` in the command, `{{name}}` is an escape. With `shell` the `${...}`
+/// that sh expands itself are left out.
+fn placeholders(command: &str, shell: bool) -> Vec<&str> {
+    let tokens: Vec<&str> = command.split('{').collect();
+    (1..tokens.len()).filter(|&i| i < 2 || !tokens[i - 1].is_empty()).filter(|&i| !shell || !tokens[i - 1].ends_with('$'))
+        .filter_map(|i| tokens[i].split_once('}')).map(|(token, _)| token).collect()
+}
+
+/// The identifier of a placeholder without its modifier.
+fn placeholder_name(token: &str) -> &str {
+    Modifier::split(token).map_or(token, |(name, _)| name)
+}
+
+/// Whether the command uses `identifier`, written as `{identifier}` with --braces.
+fn in_command(command: &str, identifier: &str, braces: bool) -> bool {
+    if braces {
+        placeholders(command, false).into_iter().any(|token| placeholder_name(token) == identifier)
+    }
+    else {
+        command.contains(identifier)
+    }
+}
+
+/// The template slot of `name`, 0 for the index and the wordlist after it.
+fn slot(name: &str, index: &str, loaded_wordlist: &[(String, Arc<Wordlist>)]) -> Option<usize> {
+    if !index.is_empty() && name == index {
+        Some(0)
+    }
+    else {
+        loaded_wordlist.iter().position(|(identifier, _)| identifier == name).map(|j| j + 1)
+    }
+}
+
+/// The command split at every identifier: the slot substituted there, its modifier and the text before it.
+type Template = Vec<(usize, Option<Modifier>, String)>;
+
+fn precompute_template(command: &str, index: &str, braces: bool, loaded_wordlist: &[(String, Arc<Wordlist>)]) -> Template {
+    if braces {
+        return precompute_braced_template(command, index, loaded_wordlist);
+    }
+    let mut template: Template = vec![];
+    let mut tmp = String::new();
+    let mut i = 0;
+    while i < command.len() {
+        if let Some(name) = escaped(&command[i..], index, loaded_wordlist) {
+            tmp.push_str(name);
+            i += name.len() + 4;
+            continue;
+        }
+        if let Some((slot, modifier, len)) = modified(&command[i..], index, loaded_wordlist) {
+            template.push((slot, Some(modifier), std::mem::take(&mut tmp)));
+            i += len;
+            continue;
+        }
+        // The longest identifier wins, so N10 of --combinations isn't taken for N1 followed by a 0.
+        let longest = std::iter::once((0, index)).filter(|(_, index)| !index.is_empty())
+            .chain(loaded_wordlist.iter().enumerate().map(|(j, (identifier, _))| (j+1, identifier.as_str())))
+            .filter(|(_, identifier)| command[i..].starts_with(identifier))
+            .max_by_key(|(_, identifier)| identifier.len());
+        match longest {
+            Some((slot, identifier)) => {
+                template.push((slot, None, tmp)); 
+                tmp = String::new();
+                i += identifier.len();
+            }
+            None => {
+                let len = command[i..].chars().next().map_or(1, char::len_utf8);
+                tmp.push_str(&command[i..i+len]);
+                i += len;
+            }
+        }
+    }
+    template.push((0, None, tmp)); 
+    template
+}
+
+/// The identifier `rest` starts with in double braces, `{{identifier}}` keeps what would be substituted as it is.
+fn escaped<'a>(rest: &'a str, index: &str, loaded_wordlist: &[(String, Arc<Wordlist>)]) -> Option<&'a str> {
+    let (name, _) = rest.strip_prefix("{{")?.split_once("}}")?;
+    slot(name, index, loaded_wordlist).map(|_| name)
+}
+
+/// The slot and modifier of the `{identifier:modifier}` that `rest` starts with, and its length.
+fn modified(rest: &str, index: &str, loaded_wordlist: &[(String, Arc<Wordlist>)]) -> Option<(usize, Modifier, usize)> {
+    let (token, _) = rest.strip_prefix('{')?.split_once('}')?;
+    let (name, modifier) = Modifier::split(token)?;
+    Some((slot(name, index, loaded_wordlist)?, modifier, token.len() + 2))
+}
+
+/// Like `precompute_template` but only `{identifier}` is substituted, other braces are kept as they are.
+fn precompute_braced_template(command: &str, index: &str, loaded_wordlist: &[(String, Arc<Wordlist>)]) -> Template {
+    let mut template: Template = vec![];
+    let mut tmp = String::new();
+    let mut rest = command;
+    while let Some(open) = rest.find('{') {
+        tmp.push_str(&rest[..open]);
+        rest = &rest[open..];
+        if let Some(name) = escaped(rest, index, loaded_wordlist) {
+            tmp.push_str(&rest[1..name.len() + 3]);
+            rest = &rest[name.len() + 4..];
+            continue;
+        }
+        if let Some((slot, modifier, len)) = modified(rest, index, loaded_wordlist) {
+            template.push((slot, Some(modifier), std::mem::take(&mut tmp)));
+            rest = &rest[len..];
+            continue;
+        }
+        let name = rest[1..].find('}').map(|close| &rest[1..close + 1]);
+        match name.and_then(|name| Some((name, slot(name, index, loaded_wordlist)?))) {
+            Some((name, slot)) => {
+                template.push((slot, None, std::mem::take(&mut tmp)));
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                tmp.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    tmp.push_str(rest);
+    template.push((0, None, tmp));
+    template
+}
+
+/// Splits a --no-shell command into arguments like sh would, without expanding anything.
+fn split_args(command: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                args.extend(arg.take());
+                continue;
+            }
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => arg.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    if args.is_empty() {
+        return Err("empty command".to_string());
+    }
+    Ok(args)
+}
+
+/// The combinations of the wordlists run by `job`, which are several with --max-args.
+fn combinations(job: usize, max_args: usize, space: &Space) -> Vec<Vec<usize>> {
+    (job * max_args..space.total().min((job + 1).saturating_mul(max_args))).map(|nth| space.lines(nth)).collect()
+}
+
+/// The command of job `idx`, with every value single-quoted for the shell if `quote`.
+fn gen_command(template: &[(usize, Option<Modifier>, String)], idx: usize, loaded_wordlist: &[(String, Arc<Wordlist>)], space: &Space, max_args: usize, quote: bool) -> String {
+    let mut command = String::new();
+    let idxs = combinations(idx, max_args, space);
+    for tvalue in &template[..template.len()-1] {
+        command.push_str(&tvalue.2);
+        if tvalue.0 == 0 {
+            let idx = idx.to_string();
+            command.push_str(&tvalue.1.map_or(Cow::Borrowed(idx.as_str()), |modifier| modifier.apply(&idx)));
+        }
+        else {
+            for (i, idxs) in idxs.iter().enumerate() {
+                if i > 0 {
+                    command.push(' ');
+                }
+                let value = modified_value(&loaded_wordlist[tvalue.0-1].1, idxs[tvalue.0-1], tvalue.1);
+                if quote {
+                    command.push_str(&String::from_utf8_lossy(&Sh::quote_vec(value.as_ref())));
+                }
+                else {
+                    command.push_str(&value);
+                }
+            }
+        }
+    }
+    command.push_str(&template[template.len()-1].2);
+    command
+}
+
+fn modified_value(lines: &Wordlist, line: usize, modifier: Option<Modifier>) -> Cow<'_, str> {
+    let value = lines.get(line);
+    match modifier {
+        Some(modifier) => Cow::Owned(modifier.apply(&value).into_owned()),
+        None => value,
+    }
+}
+
+/// The arguments of job `idx` for --no-shell. A word that is only an identifier becomes an argument per value with
+/// --max-args like xargs does, the values of one inside a longer word are joined by spaces.
+fn gen_argv(templates: &[Template], idx: usize, loaded_wordlist: &[(String, Arc<Wordlist>)], space: &Space, max_args: usize) -> Vec<String> {
+    let mut argv = vec![];
+    for template in templates {
+        match template.as_slice() {
+            [(slot @ 1.., modifier, before), (_, _, after)] if before.is_empty() && after.is_empty() => {
+                for idxs in combinations(idx, max_args, space) {
+                    argv.push(modified_value(&loaded_wordlist[slot-1].1, idxs[slot-1], *modifier).into_owned());
+                }
+            }
+            _ => argv.push(gen_command(template, idx, loaded_wordlist, space, max_args, false)),
+        }
+    }
+    argv
+}
+
+/// The value of each wordlist in `idx`, separated by spaces with --max-args.
+fn job_values<'a>(idx: usize, loaded_wordlist: &'a [(String, Arc<Wordlist>)], space: &Space, max_args: usize) -> Vec<Cow<'a, str>> {
+    let idxs = combinations(idx, max_args, space);
+    loaded_wordlist.iter().enumerate().map(|(i, (_, lines))| match idxs.as_slice() {
+        [idxs] => lines.get(idxs[i]),
+        idxs => Cow::Owned(idxs.iter().map(|idxs| lines.get(idxs[i])).collect::<Vec<_>>().join(" ")),
+    }).collect()
+}
+
+/// Lets a --stream wordlist forget the lines of a finished job.
+fn release_job(idx: usize, loaded_wordlist: &[(String, Arc<Wordlist>)], space: &Space, max_args: usize) {
+    for idxs in combinations(idx, max_args, space) {
+        for ((_, lines), i) in loaded_wordlist.iter().zip(idxs) {
+            lines.release(i);
+        }
+    }
+}
+
+fn job_tag(idx: usize, loaded_wordlist: &[(String, Arc<Wordlist>)], space: &Space, max_args: usize) -> String {
+    if loaded_wordlist.is_empty() {
+        return format!("[{}] ", idx);
+    }
+    format!("[{}] ", job_values(idx, loaded_wordlist, space, max_args).join("/"))
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "parel", version = env!("CARGO_PKG_VERSION"), about = "Parallization CLI tool", disable_version_flag = true)]
+struct Cli {
+    #[arg(help="The command to run, every identifier in it is replaced by a line of its wordlist and {{identifier}} keeps the identifier itself, with --braces only {identifier} is replaced and {{identifier}} keeps {identifier}. {identifier:basename} or {identifier/} takes the last part of a path, {identifier:noext} or {identifier.} leaves out its extension, {identifier:ext} only takes the extension, {identifier:dir} or {identifier//} the directory and {identifier:upper} or {identifier:lower} changes the case")]
+    command: String,
+    #[arg(short, long, default_value_t=10, help="Number of threads")]
+    threads: usize,
+    #[arg(long, help="Run the jobs of each thread in one long-lived sh instead of a new one per job, so they share its working directory and variables")]
+    persistent_shell: bool,
+    #[arg(long, conflicts_with="persistent_shell", help="Split the command into arguments and run it directly instead of through sh, so values are never interpreted by a shell")]
+    no_shell: bool,
+    #[arg(short, long, conflicts_with="no_shell", help="Single-quote every substituted value for the shell, so spaces, quotes, $() or ; in wordlists stay part of the value")]
+    quote: bool,
+    #[arg(long="async", conflicts_with_all=["persistent_shell", "interactive"], help="Run jobs as tasks on a few threads instead of a thread each, for thousands of mostly waiting jobs")]
+    asynchronous: bool,
+    #[arg(short='n', long, default_value_t=1, help="Substitute this many consecutive combinations into each command, with the values separated by spaces like xargs -n")]
+    max_args: usize,
+    #[arg(long, default_value_t=1, help="Number of jobs a thread claims at once, fewer claims speed up many very short jobs")]
+    chunk: usize,
+    #[arg(long, default_value=None, value_parser=parse_show, help="Show nth command that will be executed (0 indexed) [example: 5 | 0-10 | 5,9,100 | last]")]
+    show: Option<ShowSpec>,
+    #[arg(long, default_value_t=0, help="Skip this many jobs at the start (same numbers as in --show)")]
+    offset: usize,
+    #[arg(long, help="Only run this many jobs, starting at --offset")]
+    limit: Option<usize>,
+    #[arg(long, default_value_t=1, help="Only run every Nth job, starting at --offset")]
+    step: usize,
+    #[arg(long, value_parser=parse_shard, help="Split the jobs into n interleaved shards and only run the ith, counting from 1 [example: 2/5]")]
+    shard: Option<Shard>,
+    #[arg(long, conflicts_with_all=["stream", "shuffle", "sample"], help="Run the jobs from last to first")]
+    reverse: bool,
+    #[arg(long, conflicts_with="stream", help="Run the jobs in a random order")]
+    shuffle: bool,
+    #[arg(long, value_name="N", conflicts_with_all=["stream", "shuffle"], help="Only run a random sample of N jobs")]
+    sample: Option<usize>,
+    #[arg(long, help="Seed of --shuffle and --sample, the same seed always gives the same jobs in the same order")]
+    seed: Option<u64>,
+    #[arg(long, help="Print every command in the order it would be executed, without executing")]
+    dry_run: bool,
+    #[arg(long, help="Ask for confirmation before running each command")]
+    interactive: bool,
+    #[arg(short, long, help="Identifier for index of running job (same number as in --show {})")]
+    index: Option<String>,
+    #[arg(long, help="Only substitute identifiers written in braces, so they don't match inside other words [example: 'curl {host}/{path}']")]
+    braces: bool,
+    #[arg(long, help="Fail when a CSV or SQL column isn't in the command, or the command has a {name} that isn't an identifier (${name} is left to the shell)")]
+    strict: bool,
+    #[arg(short, long, allow_hyphen_values=true, help="A file and an identifier used in command, an identifier given several files takes the lines of all of them, several identifiers take the columns of a CSV (or .tsv) file, - reads stdin [example: abc.txt:foo | hosts.csv:host,port | -:foo]")]
+    file: Vec<String>,
+    #[arg(short='0', long, help="Separate the entries of wordlists by NUL instead of newlines, like the output of find -print0")]
+    null: bool,
+    #[arg(long, value_parser=parse_delimiter, conflicts_with="null", help="Separate the entries of wordlists by this instead of newlines, with \\0 \\n \\t \\r \\\\ and \\xHH escapes, an empty one splits on blank lines [example: ';;' | '\\0' | '']")]
+    delimiter: Option<Separator>,
+    #[arg(long, help="Leave out wordlist entries that are empty or only whitespace")]
+    skip_empty: bool,
+    #[arg(long, value_name="PREFIX", help="Leave out wordlist entries starting with this, after any leading whitespace [example: '#']")]
+    comment_prefix: Option<String>,
+    #[arg(long, help="Strip whitespace from the start and end of every wordlist entry")]
+    trim: bool,
+    #[arg(long, help="Keep wordlist entries that aren't valid UTF-8, replacing the invalid bytes with U+FFFD")]
+    lossy: bool,
+    #[arg(long, conflicts_with="stream", help="Leave out wordlist lines that came before, also across the files of an identifier")]
+    unique: bool,
+    #[arg(long, help="Skip jobs whose command is the same as one that already ran, they count as skipped")]
+    dedup_commands: bool,
+    #[arg(long, value_enum, num_args=0..=1, default_missing_value="asc", conflicts_with="stream", help="Sort the lines of every wordlist, the rows of a CSV file by its first column")]
+    sort: Option<Sort>,
+    #[arg(long, value_name="FILE", help="A CSV file with a header row, every column in command by the name of its header")]
+    csv: Vec<String>,
+    #[arg(long, value_name="COMMAND:IDENTIFIER", help="Run a command at startup, the lines it prints are a wordlist used like -f [example: 'kubectl get pods -o name:pod']")]
+    file_cmd: Vec<String>,
+    #[arg(long, value_name="START-END[/STEP]:IDENTIFIER", value_parser=parse_range, allow_hyphen_values=true, help="The numbers from start to end as a wordlist used like -f, without a file [example: 1-65535:port | 0-100/5:percent]")]
+    range: Vec<NumberRange>,
+    #[arg(long, value_name="PATTERN:IDENTIFIER", help="The paths matching a glob pattern as a wordlist used like -f, ** matches any number of directories [example: 'data/**/*.csv:file']")]
+    glob: Vec<String>,
+    #[arg(long, value_name="START..END[/UNIT]:IDENTIFIER[:FORMAT]", value_parser=parse_dates, help="Every hour, day (the default), week or month from start up to end as a wordlist used like -f, formatted like strftime [example: 2023-01-01..2024-01-01:day | 2024-01-01..2024-07-01/month:m:%Y%m]")]
+    dates: Vec<(Dates, String)>,
+    #[arg(long, value_name="MASK:IDENTIFIER", value_parser=parse_mask, help="Every string matching a mask as a wordlist used like -f, ?l ?u ?d ?s ?a ?h ?H are lowercase, uppercase, digits, specials, all of those and hex like in hashcat [example: '?l?l?d?d:code']")]
+    mask: Vec<(Mask, String)>,
+    #[arg(long, value_name="DB:QUERY", help="An SQLite query, every column of its result in command by its name [example: 'targets.db:SELECT host, port FROM targets']")]
+    sql: Vec<String>,
+    #[arg(long, help="Take line i of every -f wordlist together instead of every combination of their lines")]
+    link: bool,
+    #[arg(long, value_name="K", conflicts_with_all=["link", "stream"], help="Take every set of K different lines of a single -f wordlist, in the command as the identifier followed by 1 to K [example: -f files.txt:item --combinations 2 'diff item1 item2']")]
+    combinations: Option<usize>,
+    #[arg(long, value_name="K", conflicts_with_all=["link", "stream", "combinations"], help="Take every ordering of K different lines of a single -f wordlist, named like with --combinations")]
+    permutations: Option<usize>,
+    #[arg(long, conflicts_with_all=["show", "stats", "checkpoint", "resume", "resume_failed"], help="Read the lines of a single -f wordlist as jobs need them instead of loading the whole file into memory")]
+    stream: bool,
+    #[arg(long, conflicts_with="stream", help="Map the wordlists into memory and only keep where each line starts, which starts faster and uses less memory for big files")]
+    mmap: bool,
+    #[arg(short, long, help="Don't show command stdout or stderr")]
+    silent: bool,
+    #[arg(long, help="Don't show command stdout")]
+    no_stdout: bool,
+    #[arg(long, help="Don't show command stderr")]
+    no_stderr: bool,
+    #[arg(long, help="Prefix every output line with the values of the job [example: [host1/admin] ...]")]
+    tag: bool,
+    #[arg(short, long, help="Print the output of jobs in the order they were started")]
+    keep_order: bool,
+    #[arg(long, conflicts_with="keep_order", help="Stream output of running jobs, but only ever print whole lines")]
+    line_buffer: bool,
+    #[arg(long, conflicts_with="line_buffer", help="Print the output of each job as one block after a header with its command and exit code")]
+    group: bool,
+    #[arg(long, conflicts_with="line_buffer", help="Only print the output of failed jobs, preceded by their command")]
+    failed_only: bool,
+    #[arg(long, value_parser=parse_regex, help="Only print output lines matching this regex; with --group, only jobs with a match are shown")]
+    grep: Option<Regex>,
+    #[arg(long, value_parser=parse_regex, help="Don't print output lines matching this regex")]
+    grep_v: Option<Regex>,
+    #[arg(long, visible_alias="find-first", value_parser=parse_regex, help="Stop all jobs as soon as one prints a line matching this regex and only show that job")]
+    until_match: Option<Regex>,
+    #[arg(short, long, help="Print each command with its index to stderr before running it")]
+    verbose: bool,
+    #[arg(long, value_name="N", num_args=0..=1, default_missing_value="5", help="Report job duration statistics and the N slowest jobs at the end [default: 5]")]
+    stats: Option<usize>,
+    #[arg(long, conflicts_with="progress", help="Show a dashboard with running jobs, failures and throughput instead of job output")]
+    tui: bool,
+    #[arg(long, requires="progress", help="Show the command each thread is running below the progress bar")]
+    progress_workers: bool,
+    #[arg(long, default_value=PROGRESS_STYLE, help="indicatif template of the progress bar, {rate} shows jobs per second and {msg} the failures")]
+    progress_style: String,
+    #[arg(long, value_name="PATH", help="Keep a JSON file with the counts and ETA of the run up to date")]
+    progress_file: Option<String>,
+    #[arg(long, default_value="#>-", help="Characters used to draw the filled, current and empty part of the progress bar")]
+    progress_chars: String,
+    #[arg(long, default_value="10", value_parser=parse_progress_interval, help="When stderr isn't a terminal, print a progress line this often with --progress [example: 30 | 500j]")]
+    progress_interval: ProgressInterval,
+    #[arg(short, long, help="Enable progress bar")]
+    progress: bool,
+    #[arg(long, value_parser=parse_seconds, help="Kill jobs running longer than this many seconds")]
+    timeout: Option<Duration>,
+    #[arg(long, default_value_t=0, help="Retry failed jobs up to this many times")]
+    retries: usize,
+    #[arg(long, value_parser=parse_seconds, help="Seconds to wait before retrying a failed job")]
+    retry_delay: Option<Duration>,
+    #[arg(long, default_value_t=1.0, value_parser=parse_backoff, help="Multiply the retry delay by this factor after every attempt")]
+    retry_backoff: f64,
+    #[arg(long, value_delimiter=',', help="Only retry jobs exiting with one of these codes [example: 28,52]")]
+    retry_on_exit: Vec<i32>,
+    #[arg(long, value_parser=parse_halt, help="Stop dispatching jobs after N failures, 'now' also kills running jobs [example: now,fail=5]")]
+    halt: Option<HaltPolicy>,
+    #[arg(long, help="Exit with 0 even if jobs failed or --halt stopped the run (otherwise 1 and 2)")]
+    no_fail_exit: bool,
+    #[arg(long, conflicts_with="no_fail_exit", help="Exit with the number of failed jobs, capped at 101 like GNU parallel")]
+    exit_code_count: bool,
+    #[arg(long, help="Append a line per finished job to this file (GNU parallel joblog format)")]
+    joblog: Option<String>,
+    #[arg(long, requires="joblog", help="Skip jobs already recorded in the joblog")]
+    resume: bool,
+    #[arg(long, requires="joblog", conflicts_with="resume", help="Only re-run jobs recorded as failed in the joblog")]
+    resume_failed: bool,
+    #[arg(long, help="Append a JSON object with the values, command, status and output of every finished job to this file")]
+    json: Option<String>,
+    #[arg(long, help="Record the values, command, status, timings and output of every finished job in this SQLite database")]
+    sqlite: Option<String>,
+    #[arg(long, help="Save stdout, stderr, exitcode and metadata of every job in DIR/<index>/")]
+    results: Option<String>,
+    #[arg(long, help="Write the combined output of each job to this file, substituted like the command [example: logs/host_port.log]")]
+    output_file: Option<String>,
+    #[arg(long, value_enum, help="Compress the files written by --results and --output-file (--results adds .gz or .zst)")]
+    compress: Option<Compression>,
+    #[arg(long, help="Periodically save progress to this file and continue from it when it exists")]
+    checkpoint: Option<String>,
+    #[arg(long, help="Show a desktop notification when the run ends")]
+    notify: bool,
+    #[arg(long, help="Ring the terminal bell when the run ends")]
+    bell: bool,
+    #[arg(long, help="Ask the terminal to show a notification when the run ends (OSC 9)")]
+    osc_notify: bool,
+    #[arg(long, value_name="URL", help="POST a JSON summary to this URL when the run ends")]
+    webhook: Option<String>,
+    #[arg(long, value_name="N", requires="webhook", help="Also POST to --webhook as soon as N jobs failed")]
+    webhook_failures: Option<usize>,
+    #[arg(long, value_name="ADDR", help="Serve Prometheus metrics over HTTP on this address [example: 127.0.0.1:9123]")]
+    metrics_listen: Option<String>,
+    #[arg(long, value_name="PATH", help="Accept status, pause, resume, set-threads N and abort commands on this unix socket")]
+    control_socket: Option<String>,
+    #[arg(long, help="Export a span per job over OTLP/HTTP, configured with the OTEL_EXPORTER_OTLP_* environment variables")]
+    otel: bool,
+    #[arg(long, action = clap::builder::ArgAction::Version)]
+    version: (),
+}
+
+const PROGRESS_STYLE: &str = "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}{msg:.red} ({percent}%) {rate} ETA {eta}";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+const PROGRESS_FILE_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_FAILURES: usize = 100;
+const PAUSE_INTERVAL: Duration = Duration::from_millis(100);
+const SUPERVISOR_INTERVAL: Duration = Duration::from_millis(20);
+
+fn main() {
+    let args = Cli::parse();
+    let command = args.command;
+
+    let index = if let Some(index) = args.index {
+        if !is_alphanumeric(&index) {
+            eprintln!("{} Identifier {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), index);
+            std::process::exit(1);
+        }
+        index
+    }
+    else {
+        "".to_string()
+    };
+
+    if args.threads == 0 {
+        eprintln!("{} --threads must be at least 1", "error:".red().bold());
+        std::process::exit(1);
+    }
+    if args.max_args == 0 {
+        eprintln!("{} --max-args must be at least 1", "error:".red().bold());
+        std::process::exit(1);
+    }
+    if args.step == 0 {
+        eprintln!("{} --step must be at least 1", "error:".red().bold());
+        std::process::exit(1);
+    }
+    // --combinations and --permutations bind K lines of one wordlist to numbered identifiers.
+    let tuple = args.combinations.map(|k| ("--combinations", k)).or(args.permutations.map(|k| ("--permutations", k)));
+    if let Some((flag, 0)) = tuple {
+        eprintln!("{} {} must be at least 1", "error:".red().bold(), flag);
+        std::process::exit(1);
+    }
+    if args.chunk == 0 {
+        eprintln!("{} --chunk must be at least 1", "error:".red().bold());
+        std::process::exit(1);
+    }
+
+    let mut files: Vec<(String, Vec<String>)> = vec![];
+
+    for line in args.file {
+        let mut identifier: String = "".to_string();
+        let mut path: String = "".to_string();
+        for j in (0..line.len()).rev() {
+            if line[j..j+1] == *":" {
+                identifier = line[j+1..].to_string();
+                path = line[..j].to_string();
+            }
+        }
+        if identifier.is_empty() {
+            eprintln!("{} Missing identifier, example: '-f {}:foo'", "error:".red().bold(), line);
+            std::process::exit(1);
+        }
+        // Several identifiers name the columns of a CSV file.
+        for column in identifier.split(',') {
+            if !is_alphanumeric(column) {
+                eprintln!("{} Identifier {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), column);
+                std::process::exit(1);
+            }
+            let taken = files.iter().any(|(f, _)| *f != identifier && f.split(',').any(|other| other == column));
+            if column == index || taken {
+                eprintln!("{} identifier '{}' aready exists", "error:".red().bold(), column);
+                std::process::exit(1);
+            }
+            // The numbered identifiers of --combinations and --permutations are checked once they are known.
+            if tuple.is_none() && !in_command(&command, column, args.braces) {
+                eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), column);
+                std::process::exit(1);
+            }
+        }
+        if wordlist::is_stdin(Path::new(&path)) {
+            if files.iter().any(|(_, paths)| paths.iter().any(|path| wordlist::is_stdin(Path::new(path)))) {
+                eprintln!("{} Only one wordlist can be read from stdin", "error:".red().bold());
+                std::process::exit(1);
+            }
+        }
+        else if !Path::new(&path).exists() {
+            eprintln!("{} file '{}' does not exist", "error:".red().bold(), path);
+            std::process::exit(1);
+        }
+        match files.iter_mut().find(|(f, _)| *f == identifier) {
+            Some((_, paths)) => paths.push(path),
+            None => files.push((identifier.clone(), vec![path])),
+        }
+    }
+    if !index.is_empty() && !in_command(&command, &index, args.braces) {
+        eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), index);
+        std::process::exit(1);
+    }
+
+    let mut loaded_wordlist: Vec<(String, Arc<Wordlist>)> = vec![];
+
+    let single = files.len() == 1 && !files[0].0.contains(',') && args.csv.is_empty() && args.sql.is_empty() && args.file_cmd.is_empty() && args.range.is_empty() && args.dates.is_empty() && args.mask.is_empty() && args.glob.is_empty();
+    if let Some((flag, _)) = tuple.filter(|_| !single) {
+        eprintln!("{} {} needs exactly one -f wordlist", "error:".red().bold(), flag);
+        std::process::exit(1);
+    }
+    if args.stream && (!single || files[0].1.len() != 1) {
+        eprintln!("{} --stream needs exactly one -f wordlist", "error:".red().bold());
+        std::process::exit(1);
+    }
+    let separator = match args.delimiter {
+        Some(ref separator) => separator.clone(),
+        None if args.null => Separator::Bytes(vec![0]),
+        None => Separator::newline(),
+    };
+    let format = Format { separator, skip_empty: args.skip_empty, comment_prefix: args.comment_prefix.clone(), trim: args.trim, lossy: args.lossy, invalid: Arc::default() };
+    // The wordlists that take the same line, the columns of a CSV file.
+    let mut groups: Vec<Vec<usize>> = vec![];
+    // Where the lines of identifiers given several files came from, for the summary.
+    let mut sources: Vec<(String, Vec<(String, usize)>)> = vec![];
+    for (identifier, paths) in files {
+        let columns: Vec<&str> = identifier.split(',').collect();
+        let mut parts: Vec<Vec<Wordlist>> = columns.iter().map(|_| vec![]).collect();
+        for path in &paths {
+            let lines = if columns.len() > 1 {
+                Wordlist::columns(path, columns.len(), &format)
+            }
+            else if args.stream && wordlist::is_stdin(Path::new(path)) {
+                eprintln!("{} --stream can't count the lines of stdin up front", "error:".red().bold());
+                std::process::exit(1);
+            }
+            else if args.stream {
+                Streamed::open(path, &format).map(|streamed| vec![Wordlist::Streamed(streamed)])
+            }
+            else if args.mmap && !wordlist::is_stdin(Path::new(path)) {
+                Wordlist::map(path, &format).map(|lines| vec![lines])
+            }
+            else {
+                Wordlist::load(path, &format).map(|lines| vec![lines])
+            };
+            let lines = lines.unwrap_or_else(|err| {
+                eprintln!("{} Could not read {}: {}", "error:".red().bold(), path, err);
+                std::process::exit(1);
+            });
+            warn_invalid(&format, path);
+            for (part, lines) in parts.iter_mut().zip(lines) {
+                part.push(lines);
+            }
+        }
+        if paths.len() > 1 {
+            sources.push((identifier.clone(), paths.into_iter().zip(parts[0].iter().map(Wordlist::len)).collect()));
+        }
+        let mut lines: Vec<Wordlist> = parts.into_iter().map(|mut parts| if parts.len() == 1 { parts.pop().unwrap() } else { Wordlist::Concatenated(parts) }).collect();
+        if args.unique {
+            lines = drop_duplicates(&identifier, lines);
+        }
+        if let Some(sort) = args.sort {
+            lines = wordlist::sort(lines, sort);
+        }
+        let mut columns = columns.into_iter().zip(lines).map(|(column, lines)| (column.to_string(), Arc::new(lines)));
+
+        if let Some((_, k)) = tuple {
+            let (identifier, lines) = columns.next().unwrap();
+            for i in 1..=k {
+                let identifier = format!("{}{}", identifier, i);
+                if !in_command(&command, &identifier, args.braces) {
+                    eprintln!("{} Identifier '{}' is not in command", "error:".red().bold(), identifier);
+                    std::process::exit(1);
+                }
+                loaded_wordlist.push((identifier, lines.clone()));
+            }
+            continue;
+        }
+        let start = loaded_wordlist.len();
+        loaded_wordlist.extend(columns);
+        groups.push((start..loaded_wordlist.len()).collect());
+    }
+    // Wordlists that aren't read from a file, each under one identifier.
+    let mut generated: Vec<(String, Wordlist)> = vec![];
+    for spec in &args.file_cmd {
+        let Some((cmd, identifier)) = spec.rsplit_once(':').filter(|(_, identifier)| !identifier.is_empty()) else {
+            eprintln!("{} Missing identifier, example: '--file-cmd {}:foo'", "error:".red().bold(), spec.trim_end_matches(':'));
+            std::process::exit(1);
+        };
+        match Wordlist::command(cmd, &format) {
+            Ok(lines) => {
+                warn_invalid(&format, &format!("`{}`", cmd));
+                let mut lines = vec![lines];
+                if args.unique {
+                    lines = drop_duplicates(identifier, lines);
+                }
+                if let Some(sort) = args.sort {
+                    lines = wordlist::sort(lines, sort);
+                }
+                generated.push((identifier.to_string(), lines.remove(0)));
+            }
+            Err(err) => {
+                eprintln!("{} Could not run `{}`: {}", "error:".red().bold(), cmd, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for spec in &args.glob {
+        let Some((pattern, identifier)) = spec.rsplit_once(':').filter(|(_, identifier)| !identifier.is_empty()) else {
+            eprintln!("{} Missing identifier, example: '--glob {}:foo'", "error:".red().bold(), spec.trim_end_matches(':'));
+            std::process::exit(1);
+        };
+        match Wordlist::glob(pattern) {
+            Ok(paths) => {
+                if paths.len() == 0 {
+                    eprintln!("{} No file matches {}", "warning:".yellow().bold(), pattern);
+                }
+                generated.push((identifier.to_string(), paths));
+            }
+            Err(err) => {
+                eprintln!("{} Invalid glob {}: {}", "error:".red().bold(), pattern, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for range in &args.range {
+        generated.push((range.identifier.clone(), Wordlist::Range { start: range.start, step: range.step, len: range.len }));
+    }
+    for (dates, identifier) in &args.dates {
+        generated.push((identifier.clone(), Wordlist::Dates(dates.clone())));
+    }
+    for (mask, identifier) in &args.mask {
+        generated.push((identifier.clone(), Wordlist::Mask(mask.clone())));
+    }
+    for (identifier, lines) in generated {
+        check_identifier(&identifier, &index, &command, args.braces, &loaded_wordlist);
+        groups.push(vec![loaded_wordlist.len()]);
+        loaded_wordlist.push((identifier, Arc::new(lines)));
+    }
+    // Named columns from --csv and --sql.
+    let mut tables: Vec<(&str, Vec<(String, Wordlist)>)> = vec![];
+    for path in &args.csv {
+        match Wordlist::csv(path) {
+            Ok(columns) => tables.push((path, columns)),
+            Err(err) => {
+                eprintln!("{} Could not read {}: {}", "error:".red().bold(), path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for sql in &args.sql {
+        let Some((path, query)) = sql.split_once(':') else {
+            eprintln!("{} Missing query, example: '--sql {}:SELECT host FROM targets'", "error:".red().bold(), sql);
+            std::process::exit(1);
+        };
+        if !Path::new(path).exists() {
+            eprintln!("{} file '{}' does not exist", "error:".red().bold(), path);
+            std::process::exit(1);
+        }
+        match Wordlist::sql(path, query) {
+            Ok(columns) => tables.push((path, columns)),
+            Err(err) => {
+                eprintln!("{} Could not query {}: {}", "error:".red().bold(), path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for (path, columns) in tables {
+        let start = loaded_wordlist.len();
+        for (header, lines) in columns {
+            // The other columns are left out, their headers could be spreadsheet titles that aren't identifiers.
+            if header.is_empty() || !in_command(&command, &header, args.braces) {
+                if args.strict {
+                    eprintln!("{} Column '{}' of {} is not in command", "error:".red().bold(), header, path);
+                    std::process::exit(1);
+                }
+                continue;
+            }
+            if !is_alphanumeric(&header) {
+                eprintln!("{} Column {} of {} is not alphanumeric [a-zA-Z0-9]", "error:".red().bold(), header, path);
+                std::process::exit(1);
+            }
+            if header == index || loaded_wordlist.iter().any(|(identifier, _)| *identifier == header) {
+                eprintln!("{} identifier '{}' aready exists", "error:".red().bold(), header);
+                std::process::exit(1);
+            }
+            loaded_wordlist.push((header, Arc::new(lines)));
+        }
+        if loaded_wordlist.len() == start {
+            eprintln!("{} No column of {} is in command", "error:".red().bold(), path);
+            std::process::exit(1);
+        }
+        groups.push((start..loaded_wordlist.len()).collect());
+    }
+    let wordlist_lengths: Vec<usize> = loaded_wordlist.iter().map(|(_, lines)| lines.len()).collect();
+    let space = if let Some((flag, k)) = tuple {
+        let space = if args.permutations.is_some() { Space::permutations(wordlist_lengths[0], k) } else { Space::combinations(wordlist_lengths[0], k) };
+        match space {
+            Some(space) => space,
+            None => {
+                eprintln!("{} {} {} of {} lines make more than {} combinations", "error:".red().bold(), flag, k, wordlist_lengths[0], usize::MAX);
+                std::process::exit(1);
+            }
+        }
+    }
+    else if args.link {
+        if wordlist_lengths.iter().any(|&len| len != wordlist_lengths[0]) {
+            eprintln!("{} --link wordlists differ in length, only the first {} lines of each are used", "warning:".yellow().bold(), wordlist_lengths.iter().min().unwrap());
+        }
+        Space::linked(&wordlist_lengths)
+    }
+    else {
+        match Space::product(&groups, &wordlist_lengths) {
+            Some(space) => space,
+            None => {
+                eprintln!("{} The wordlists make more than {} combinations", "error:".red().bold(), usize::MAX);
+                std::process::exit(1);
+            }
+        }
+    };
+    let total_words = space.total().div_ceil(args.max_args);
+
+
+    if !args.braces {
+        check_prefixes(&index, &loaded_wordlist, tuple.is_some());
+    }
+    if args.strict {
+        for token in placeholders(&command, true) {
+            let known = |name: &str| slot(name, &index, &loaded_wordlist).is_some();
+            if Modifier::split(token).is_none() && token.split_once(':').is_some_and(|(name, _)| known(name)) {
+                eprintln!("{} Unknown modifier in {{{}}}, expected one of {}", "error:".red().bold(), token, modifier::MODIFIERS);
+                std::process::exit(1);
+            }
+            let name = placeholder_name(token);
+            if !name.is_empty() && is_alphanumeric(name) && !known(name) {
+                eprintln!("{} {{{}}} in command is not an identifier", "error:".red().bold(), token);
+                std::process::exit(1);
+            }
+        }
+    }
+    let template = precompute_template(&command, &index, args.braces, &loaded_wordlist);
+    let argv_template = args.no_shell.then(|| match split_args(&command) {
+        Ok(argv) => argv.iter().map(|arg| precompute_template(arg, &index, args.braces, &loaded_wordlist)).collect::<Vec<_>>(),
+        Err(err) => {
+            eprintln!("{} Could not split command for --no-shell: {}", "error:".red().bold(), err);
+            std::process::exit(1);
+        }
+    });
+    let output_template = args.output_file.as_ref().map(|path| precompute_template(path, &index, args.braces, &loaded_wordlist));
+
+    if let Some(show) = args.show {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        for (start, end) in show.0 {
+            let (start, end) = (start.resolve(total_words), end.resolve(total_words));
+            if start >= total_words || end >= total_words {
+                eprintln!("{} show parameter {} cannot be more than {}", "error:".red().bold(), start.max(end), total_words);
+                std::process::exit(1);
+            }
+            for show in start..=end {
+                if writeln!(out, "{}", gen_command(&template, show, &loaded_wordlist, &space, args.max_args, args.quote)).is_err() {
+                    std::process::exit(0);
+                }
+            }
+        }
+        let _ = out.flush();
+        std::process::exit(0);
+    }
+    let logged = match args.joblog {
+        Some(ref path) if args.resume || args.resume_failed => match joblog::read(path) {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                eprintln!("{} Could not read joblog '{}': {}", "error:".red().bold(), path, err);
+                std::process::exit(1);
+            }
+        },
+        _ => HashMap::new(),
+    };
+    let mut selection = Selection::new(total_words, args.offset, args.limit, args.step);
+    if let Some(shard) = args.shard {
+        selection = selection.shard(shard.index - 1, shard.count);
+    }
+    if args.reverse {
+        selection = selection.reverse();
+    }
+    if args.shuffle {
+        if args.checkpoint.is_some() && args.seed.is_none() {
+            eprintln!("{} --shuffle with --checkpoint needs a --seed, so a continued run keeps the same order", "error:".red().bold());
+            std::process::exit(1);
+        }
+        selection = selection.shuffle(args.seed.unwrap_or_else(selection::random_seed));
+    }
+    if let Some(count) = args.sample {
+        if args.checkpoint.is_some() && args.seed.is_none() {
+            eprintln!("{} --sample with --checkpoint needs a --seed, so a continued run takes the same sample", "error:".red().bold());
+            std::process::exit(1);
+        }
+        selection = selection.sample(count, args.seed.unwrap_or_else(selection::random_seed));
+    }
+    if let Some(Wordlist::Streamed(streamed)) = loaded_wordlist.first_mut().and_then(|(_, lines)| Arc::get_mut(lines)) {
+        let max_args = args.max_args;
+        streamed.keep(move |line| selection.contains(line / max_args));
+    }
+    let mut skip: HashSet<usize> = HashSet::new();
+    let mut only: Option<Vec<usize>> = None;
+    if args.resume_failed {
+        let mut failed: Vec<usize> = logged.into_iter()
+            .filter(|&(job, status)| selection.contains(job) && status != (0, 0))
+            .map(|(job, _)| job)
+            .collect();
+        failed.sort_unstable();
+        only = Some(failed);
+    }
+    else {
+        skip = logged.into_keys().collect();
+    }
+    let pending = match only {
+        Some(ref only) => only.len(),
+        None => selection.len() - skip.iter().filter(|&&job| selection.contains(job)).count(),
+    };
+
+    let progress_style = args.progress.then(|| {
+        let style = ProgressStyle::default_bar().template(&args.progress_style).unwrap_or_else(|err| {
+            eprintln!("{} Invalid --progress-style: {}", "error:".red().bold(), err);
+            std::process::exit(1);
+        });
+        if args.progress_chars.chars().count() < 2 {
+            eprintln!("{} --progress-chars needs at least 2 characters", "error:".red().bold());
+            std::process::exit(1);
+        }
+        style
+            .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| { let _ = write!(w, "{:.1} jobs/s", state.per_sec()); })
+            .progress_chars(&args.progress_chars)
+    });
+    let progress_bar = match progress_style {
+        Some(style) if !args.dry_run && io::stderr().is_terminal() => {
+            let pb = ProgressBar::new(pending as u64);
+            pb.set_style(style);
+            pb.set_position(0);
+            Some(Arc::new(Mutex::new(pb)))
+        }
+        _ => None,
+    };
+    let multi_progress = match progress_bar {
+        Some(ref pb) if args.progress_workers => {
+            let multi = MultiProgress::new();
+            multi.add(pb.lock().unwrap().clone());
+            Some(multi)
+        }
+        _ => None,
+    };
+
+    let state = Arc::new(State::new(args.threads, args.checkpoint.is_some()));
+    if let Some(ref path) = args.checkpoint {
+        match Checkpoint::load(path) {
+            Ok(Some(checkpoint)) => {
+                if checkpoint.command != format!("{:?}", command) || checkpoint.total != total_words {
+                    eprintln!("{} Checkpoint '{}' belongs to a different command or wordlists", "error:".red().bold(), path);
+                    std::process::exit(1);
+                }
+                if checkpoint.selection != format!("{:?}", selection) {
+                    eprintln!("{} Checkpoint '{}' was written for a different selection of the jobs", "error:".red().bold(), path);
+                    std::process::exit(1);
+                }
+                eprintln!("{} Continuing from checkpoint '{}' ({} jobs done)", "info:".cyan().bold(), path, checkpoint.completed);
+                if let Some(ref pb) = progress_bar {
+                    let pb = pb.lock().unwrap();
+                    pb.set_position(checkpoint.completed as u64);
+                    if checkpoint.failed > 0 {
+                        pb.set_message(format!(" ({} failed)", checkpoint.failed));
+                    }
+                    // Jobs done before the restart would otherwise make the ETA far too optimistic.
+                    pb.reset_eta();
+                }
+                state.restore(checkpoint);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("{} Could not read checkpoint '{}': {}", "error:".red().bold(), path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    // The commands that ran so far, for --dedup-commands.
+    let commands: Option<Arc<Mutex<HashSet<String>>>> = args.dedup_commands.then(|| Arc::new(Mutex::new(HashSet::new())));
+    if args.dry_run {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        'claim: while let Some(jobs) = state.claim(&only, &skip, &selection, args.chunk) {
+            for (_, job, _) in jobs {
+                let command = gen_command(&template, job, &loaded_wordlist, &space, args.max_args, args.quote);
+                if args.stream {
+                    release_job(job, &loaded_wordlist, &space, args.max_args);
+                }
+                if commands.as_ref().is_some_and(|commands| !commands.lock().unwrap().insert(command.clone())) {
+                    continue;
+                }
+                if writeln!(out, "{}", command).is_err() {
+                    break 'claim;
+                }
+            }
+        }
+        let _ = out.flush();
+        std::process::exit(0);
+    }
+    handle_signals(state.clone(), pending);
+
+    let space = Arc::new(space);
+    let loaded_wordlist = Arc::new(loaded_wordlist);
+    let template = Arc::new(template);
+    let output_template = Arc::new(output_template);
+    let retry_on_exit = Arc::new(args.retry_on_exit);
+    let skip = Arc::new(skip);
+    let only = Arc::new(only);
+    let mode = if args.keep_order {
+        Mode::KeepOrder
+    }
+    else if args.line_buffer {
+        Mode::LineBuffer
+    }
+    else if args.group || args.failed_only || args.until_match.is_some() {
+        Mode::Group
+    }
+    else {
+        Mode::Ungrouped
+    };
+    let printer = Arc::new(Printer::new(output::Options {
+        mode,
+        headers: args.group || args.failed_only || args.until_match.is_some(),
+        failed_only: args.failed_only,
+        grep: args.grep,
+        grep_v: args.grep_v,
+        until_match: args.until_match.clone(),
+    }));
+    let exec_options = ExecOptions {
+        silent: args.silent || args.tui,
+        no_stdout: args.no_stdout,
+        no_stderr: args.no_stderr,
+        until_match: args.until_match.is_some(),
+        timeout: args.timeout,
+    };
+    let tty = if args.interactive {
+        let tty = File::open("/dev/tty").unwrap_or_else(|err| {
+            eprintln!("{} Could not open /dev/tty for --interactive: {}", "error:".red().bold(), err);
+            std::process::exit(1);
+        });
+        Some(Arc::new(Mutex::new(io::BufReader::new(tty))))
+    }
+    else { None };
+    let joblog = args.joblog.map(|path| {
+        let identifiers: Vec<&str> = loaded_wordlist.iter().map(|(identifier, _)| identifier.as_str()).collect();
+        let joblog = JobLog::open(&path, &identifiers).unwrap_or_else(|err| {
+            eprintln!("{} Could not open joblog '{}': {}", "error:".red().bold(), path, err);
+            std::process::exit(1);
+        });
+        Arc::new(joblog)
+    });
+
+    let jsonlog = args.json.map(|path| {
+        let jsonlog = JsonLog::open(&path).unwrap_or_else(|err| {
+            eprintln!("{} Could not open '{}': {}", "error:".red().bold(), path, err);
+            std::process::exit(1);
+        });
+        Arc::new(jsonlog)
+    });
+    let database = args.sqlite.map(|path| {
+        let database = Database::open(&path).unwrap_or_else(|err| {
+            eprintln!("{} Could not open database '{}': {}", "error:".red().bold(), path, err);
+            std::process::exit(1);
+        });
+        Arc::new(database)
+    });
+    let results = args.results.map(|dir| {
+        let results = Results::create(dir.clone().into(), args.compress).unwrap_or_else(|err| {
+            eprintln!("{} Could not create results directory '{}': {}", "error:".red().bold(), dir, err);
+            std::process::exit(1);
+        });
+        Arc::new(results)
+    });
+
+    let checkpointer = args.checkpoint.map(|path| {
+        let (done, finished) = mpsc::channel::<()>();
+        let state = state.clone();
+        let command = command.clone();
+        let thread = thread::spawn(move || loop {
+            let finished = finished.recv_timeout(CHECKPOINT_INTERVAL) != Err(mpsc::RecvTimeoutError::Timeout);
+            if let Err(err) = state.checkpoint(&command, &selection, total_words).save(&path) {
+                eprintln!("{} Could not write checkpoint '{}': {}", "warning:".yellow().bold(), path, err);
+            }
+            if finished {
+                break;
+            }
+        });
+        (done, thread)
+    });
+
+    let progress_file = args.progress_file.map(|path| {
+        let (done, finished) = mpsc::channel::<()>();
+        let state = state.clone();
+        let thread = thread::spawn(move || {
+            let mut done = false;
+            loop {
+                if let Err(err) = progress::write(&path, &state, pending, done) {
+                    eprintln!("{} Could not write progress file '{}': {}", "warning:".yellow().bold(), path, err);
+                }
+                if done {
+                    break;
+                }
+                done = finished.recv_timeout(PROGRESS_FILE_INTERVAL) != Err(mpsc::RecvTimeoutError::Timeout);
+            }
+        });
+        (done, thread)
+    });
+    let progress_lines = (args.progress && progress_bar.is_none()).then_some(args.progress_interval);
+    let progress_reporter = match progress_lines {
+        Some(ProgressInterval::Time(interval)) => {
+            let (done, finished) = mpsc::channel::<()>();
+            let state = state.clone();
+            let thread = thread::spawn(move || loop {
+                let finished = finished.recv_timeout(interval) != Err(mpsc::RecvTimeoutError::Timeout);
+                state.print_progress(pending);
+                if finished {
+                    break;
+                }
+            });
+            Some((done, thread))
+        }
+        _ => None,
+    };
+
+    let metrics = args.metrics_listen.map(|addr| {
+        let metrics = Arc::new(Metrics::new(pending));
+        if let Err(err) = metrics::serve(&addr, metrics.clone(), state.clone()) {
+            eprintln!("{} Could not listen on '{}': {}", "error:".red().bold(), addr, err);
+            std::process::exit(1);
+        }
+        metrics
+    });
+    if let Some(ref path) = args.control_socket {
+        if let Err(err) = control::serve(path, state.clone(), pending) {
+            eprintln!("{} Could not listen on '{}': {}", "error:".red().bold(), path, err);
+            std::process::exit(1);
+        }
+    }
+    let tracer = args.otel.then(|| Arc::new(Tracer::new()));
+    let durations: Arc<Mutex<Vec<(Duration, usize)>>> = Arc::new(Mutex::new(vec![]));
+    let started = Instant::now();
+    let worker = {
+        let space = space.clone();
+        let loaded_wordlist = loaded_wordlist.clone();
+        let template = template.clone();
+        let state = state.clone();
+        let progress_bar = progress_bar.clone();
+        let printer = printer.clone();
+        let durations = durations.clone();
+        let tracer = tracer.clone();
+        let webhook = args.webhook.clone();
+        Arc::new(async move |id: usize| {
+            let line = multi_progress.as_ref().map(|multi| {
+                let line = multi.add(ProgressBar::new_spinner());
+                line.set_style(ProgressStyle::with_template("{prefix:.cyan.bold} {wide_msg}").unwrap());
+                line
+            });
+            let mut batch = VecDeque::new();
+            let mut shell = None;
+            loop {
+                state.wait_while_paused(args.asynchronous).await;
+                if state.stopping() || (batch.is_empty() && id >= state.threads.load(Ordering::SeqCst)) {
+                    break;
+                }
+                if batch.is_empty() {
+                    match state.claim(&only, &skip, &selection, args.chunk) {
+                        Some(jobs) => batch.extend(jobs),
+                        None => {
+                            state.exhausted.store(true, Ordering::SeqCst);
+                            break;
+                        }
+                    }
+                }
+                let (seq, job, mut attempt) = batch.pop_front().unwrap();
+                let command = gen_command(&template, job, &loaded_wordlist, &space, args.max_args, args.quote);
+                if let Some(ref line) = line {
+                    line.set_prefix(format!("[{}]", job));
+                    line.set_message(command.clone());
+                }
+                let tag = args.tag.then(|| job_tag(job, &loaded_wordlist, &space, args.max_args));
+                let output = printer.job(seq, job, &command, tag);
+                let duplicate = commands.as_ref().is_some_and(|commands| !commands.lock().unwrap().insert(command.clone()));
+                if duplicate || tty.as_ref().is_some_and(|tty| !confirm(tty, &command)) {
+                    output.finish(None);
+                    if args.stream {
+                        release_job(job, &loaded_wordlist, &space, args.max_args);
+                    }
+                    state.skip(job);
+                    if let Some(ref pb) = progress_bar {
+                        pb.lock().unwrap().inc(1);
+                    }
+                    continue;
+                }
+                let argv = argv_template.as_ref().map(|templates| gen_argv(templates, job, &loaded_wordlist, &space, args.max_args));
+                let output_file = output_template.as_deref().map(|template| gen_command(template, job, &loaded_wordlist, &space, args.max_args, false));
+                let mut capture;
+                let result = loop {
+                    if args.verbose {
+                        eprintln!("{} {}", format!("[{}]", job).cyan().bold(), command);
+                    }
+                    let files = results.as_ref().and_then(|results| results.job(job).map_err(|err| {
+                        eprintln!("{} Could not create results of {}: {}", "warning:".yellow().bold(), job, err);
+                    }).ok());
+                    let log = output_file.as_ref().and_then(|path| results::create_log(Path::new(path), args.compress).map_err(|err| {
+                        eprintln!("{} Could not create output file '{}': {}", "warning:".yellow().bold(), path, err);
+                    }).ok());
+                    capture = Capture::new(files, log, jsonlog.is_some() || database.is_some());
+                    let runner = match argv {
+                        Some(ref argv) => Runner::Exec(argv),
+                        None if args.persistent_shell => Runner::Persistent(&mut shell),
+                        None => Runner::Sh,
+                    };
+                    let result = match runner {
+                        _ if args.asynchronous => async_exec::execute(&command, job, exec_options, &output, &capture, &state, argv.as_deref()).await,
+                        runner => execute_command(&command, job, exec_options, &output, &capture, &state, runner),
+                    };
+                    if attempt >= args.retries || !should_retry(result.status, &retry_on_exit) || state.stopping() {
+                        break result;
+                    }
+                    attempt += 1;
+                    state.retrying(job, attempt);
+                    output.clear();
+                    if !args.silent && !args.tui {
+                        eprintln!("{} Retrying {} ({}/{})", "warning:".yellow().bold(), job, attempt, args.retries);
+                    }
+                    if let Some(delay) = args.retry_delay {
+                        async_exec::sleep(args.asynchronous, retry_delay(delay, args.retry_backoff, attempt)).await;
+                    }
+                };
+
+                if let Some(ref joblog) = joblog {
+                    let values = job_values(job, &loaded_wordlist, &space, args.max_args);
+                    let values: Vec<&str> = values.iter().map(|value| value.as_ref()).collect();
+                    if let Err(err) = joblog.record(job, &result, &command, &values) {
+                        eprintln!("{} Could not write joblog: {}", "warning:".yellow().bold(), err);
+                    }
+                }
+                if capture.active() || tracer.is_some() {
+                    let values = job_values(job, &loaded_wordlist, &space, args.max_args);
+                    let values: Vec<(&str, &str)> = loaded_wordlist.iter().map(|(identifier, _)| identifier.as_str()).zip(values.iter().map(|value| value.as_ref())).collect();
+                    if let Some(ref tracer) = tracer {
+                        tracer.record(job, &result, &command, &values);
+                    }
+                    let captured = capture.take_output();
+                    if let Some(ref jsonlog) = jsonlog {
+                        if let Err(err) = jsonlog.record(job, &result, &command, &values, &captured) {
+                            eprintln!("{} Could not write json: {}", "warning:".yellow().bold(), err);
+                        }
+                    }
+                    if let Some(ref database) = database {
+                        if let Err(err) = database.record(job, &result, &command, &values, &captured) {
+                            eprintln!("{} Could not write to database: {}", "warning:".yellow().bold(), err);
+                        }
+                    }
+                    if let Err(err) = capture.finish(&result, &command, &values) {
+                        eprintln!("{} Could not write results of {}: {}", "warning:".yellow().bold(), job, err);
+                    }
+                }
+                let cancelled = printer.found() && !output.won() && result.status == JobStatus::Signaled(libc::SIGKILL);
+                output.finish(Some(result.status));
+                if args.stream {
+                    release_job(job, &loaded_wordlist, &space, args.max_args);
+                }
+                if cancelled {
+                    state.cancel(job);
+                    if let Some(ref pb) = progress_bar {
+                        pb.lock().unwrap().inc(1);
+                    }
+                    continue;
+                }
+                if args.stats.is_some() {
+                    durations.lock().unwrap().push((result.runtime, job));
+                }
+                if let Some(ref metrics) = metrics {
+                    metrics.observe(result.runtime);
+                }
+                let failed = state.finish(job, result.status.success());
+                if !result.status.success() {
+                    state.record_failure(job, result.status, &command);
+                }
+                if let (Some(ref url), Some(threshold)) = (&webhook, args.webhook_failures) {
+                    if !result.status.success() && failed == threshold {
+                        notify::webhook(url, &webhook_payload(&state, pending, "failures"));
+                    }
+                }
+                if let Some(halt) = args.halt {
+                    if !result.status.success() && failed >= halt.fail && !state.halted.swap(true, Ordering::SeqCst) {
+                        eprintln!("{} Halting after {} failed jobs", "error:".red().bold(), failed);
+                        if halt.now {
+                            state.kill_running(libc::SIGKILL);
+                        }
+                    }
+                }
+
+                if let Some(ref pb) = progress_bar {
+                    let pb = pb.lock().unwrap();
+                    if !result.status.success() {
+                        pb.set_message(format!(" ({} failed)", failed));
+                    }
+                    pb.inc(1);
+                }
+                if let Some(ProgressInterval::Jobs(jobs)) = progress_lines {
+                    if state.completed.load(Ordering::SeqCst).is_multiple_of(jobs) {
+                        state.print_progress(pending);
+                    }
+                }
+            }
+            // Lets --keep-order print past the jobs that were claimed but never run.
+            for (seq, job, _) in batch {
+                printer.job(seq, job, "", None).finish(None);
+            }
+            if let Some(line) = line {
+                line.finish_and_clear();
+            }
+        })
+    };
+
+    // Without --async the workers never wait on tokio, so one runtime without drivers runs them on all their threads.
+    let runtime = if args.asynchronous {
+        tokio::runtime::Builder::new_multi_thread().enable_all().build()
+    }
+    else {
+        tokio::runtime::Builder::new_current_thread().build()
+    };
+    let runtime = runtime.unwrap_or_else(|err| {
+        eprintln!("{} Could not start the worker runtime: {}", "error:".red().bold(), err);
+        std::process::exit(1);
+    });
+    let tui = args.tui.then(|| tui::Tui::start(state.clone(), pending).unwrap_or_else(|err| {
+        eprintln!("{} Could not start --tui: {}", "error:".red().bold(), err);
+        std::process::exit(1);
+    }));
+    run_workers(&state, |id| {
+        let worker = worker.clone();
+        if args.asynchronous {
+            WorkerHandle::Task(runtime.spawn(async move { worker(id).await }))
+        }
+        else {
+            let runtime = runtime.handle().clone();
+            WorkerHandle::Thread(thread::spawn(move || runtime.block_on(worker(id))))
+        }
+    });
+    drop(runtime);
+    drop(worker);
+    printer.flush();
+    if let Some(tui) = tui {
+        tui.finish();
+    }
+    if let Some(ref path) = args.control_socket {
+        control::remove(path);
+    }
+    if let Some((done, thread)) = checkpointer {
+        drop(done);
+        let _ = thread.join();
+    }
+    if let Some((done, thread)) = progress_file {
+        drop(done);
+        let _ = thread.join();
+    }
+    if let Some((done, thread)) = progress_reporter {
+        drop(done);
+        let _ = thread.join();
+    }
+    if let Some(ref pb) = progress_bar {
+        pb.lock().unwrap().finish();
+    }
+    if let Some(tracer) = tracer.and_then(Arc::into_inner) {
+        tracer.finish(pending, state.failed.load(Ordering::SeqCst));
+    }
+    if !args.silent {
+        print_summary(&state, pending, selection.sampled_from(), started.elapsed());
+        for (identifier, paths) in &sources {
+            let paths: Vec<String> = paths.iter().map(|(path, len)| format!("{} lines from {}", len, path)).collect();
+            eprintln!("{} {} took {}", "info:".cyan().bold(), identifier, paths.join(", "));
+        }
+    }
+    if args.notify {
+        let (title, body) = outcome(&state, pending);
+        notify::desktop(&title, &body);
+    }
+    if let Some(ref url) = args.webhook {
+        let event = if state.interrupted.load(Ordering::SeqCst) != 0 { "interrupted" }
+            else if state.halted.load(Ordering::SeqCst) { "halted" }
+            else { "finished" };
+        notify::webhook(url, &webhook_payload(&state, pending, event));
+    }
+    if args.bell && !args.silent {
+        notify::bell();
+    }
+    if args.osc_notify && !args.silent {
+        let (title, body) = outcome(&state, pending);
+        notify::osc9(&format!("{}: {}", title, body));
+    }
+    if let Some(slowest) = args.stats {
+        print_stats(&mut durations.lock().unwrap(), slowest, |job| gen_command(&template, job, &loaded_wordlist, &space, args.max_args, args.quote));
+    }
+    let signal = state.interrupted.load(Ordering::SeqCst);
+    if signal != 0 {
+        eprintln!("{} Interrupted by {} after {} of {} jobs ({} failed)", "warning:".yellow().bold(),
+            signal_name(signal), state.completed.load(Ordering::SeqCst), total_words, state.failed.load(Ordering::SeqCst));
+        std::process::exit(128 + signal);
+    }
+    if args.until_match.is_some() {
+        if !printer.found() {
+            eprintln!("{} No job printed a line matching --until-match", "warning:".yellow().bold());
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.no_fail_exit {
+        return;
+    }
+    if args.exit_code_count {
+        std::process::exit(state.failed.load(Ordering::SeqCst).min(101) as i32);
+    }
+    if state.halted.load(Ordering::SeqCst) {
+        std::process::exit(2);
+    }
+    if state.failed.load(Ordering::SeqCst) > 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wordlists(names: &[&str], len: usize) -> Vec<(String, Arc<Wordlist>)> {
+        names.iter().map(|name| (name.to_string(), Arc::new(Wordlist::Loaded((0..len).map(|line| format!("v{}", line)).collect())))).collect()
+    }
+
+    #[test]
+    fn seconds_are_positive() {
+        assert_eq!(parse_seconds("1.5"), Ok(Duration::from_millis(1500)));
+        assert!(parse_seconds("0").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("soon").is_err());
+    }
+
+    #[test]
+    fn retry_delays_grow_by_the_backoff() {
+        assert_eq!(parse_backoff("2"), Ok(2.0));
+        assert!(parse_backoff("0.5").is_err());
+        assert!(parse_backoff("NaN").is_err());
+        let delay = Duration::from_secs(1);
+        assert_eq!(retry_delay(delay, 2.0, 1), delay);
+        assert_eq!(retry_delay(delay, 2.0, 3), Duration::from_secs(4));
+        assert_eq!(retry_delay(delay, 10.0, usize::MAX), Duration::MAX);
+    }
+
+    #[test]
+    fn halt_policies() {
+        let halt = parse_halt("now,fail=5").unwrap();
+        assert!(halt.now && halt.fail == 5);
+        let halt = parse_halt("fail=1").unwrap();
+        assert!(!halt.now && halt.fail == 1);
+        assert!(parse_halt("now").is_err());
+        assert!(parse_halt("fail=0").is_err());
+        assert!(parse_halt("later,fail=1").is_err());
+    }
+
+    #[test]
+    fn show_ranges_resolve_last() {
+        let ShowSpec(ranges) = parse_show("5,0-2,last").unwrap();
+        let ranges: Vec<(usize, usize)> = ranges.into_iter().map(|(start, end)| (start.resolve(10), end.resolve(10))).collect();
+        assert_eq!(ranges, [(5, 5), (0, 2), (9, 9)]);
+        assert!(parse_show("1-x").is_err());
+        assert!(parse_show("").is_err());
+    }
+
+    #[test]
+    fn invalid_regexes_are_rejected() {
+        assert!(parse_regex("^a.*").unwrap().is_match(b"abc"));
+        assert!(parse_regex("a(").is_err());
+    }
+
+    #[test]
+    fn progress_intervals_are_seconds_or_jobs() {
+        assert!(matches!(parse_progress_interval("500j"), Ok(ProgressInterval::Jobs(500))));
+        assert!(matches!(parse_progress_interval("2.5s"), Ok(ProgressInterval::Time(time)) if time == Duration::from_millis(2500)));
+        assert!(matches!(parse_progress_interval("10"), Ok(ProgressInterval::Time(time)) if time == Duration::from_secs(10)));
+        assert!(parse_progress_interval("0j").is_err());
+        assert!(parse_progress_interval("j").is_err());
+    }
+
+    #[test]
+    fn no_shell_words_take_one_argument_per_value() {
+        let loaded_wordlist = wordlists(&["W"], 3);
+        let space = Space::product(&[vec![0]], &[3]).unwrap();
+        let templates: Vec<_> = ["printf", "W", "x=W"].iter().map(|arg| precompute_template(arg, "", false, &loaded_wordlist)).collect();
+        assert_eq!(gen_argv(&templates, 0, &loaded_wordlist, &space, 3), ["printf", "v0", "v1", "v2", "x=v0 v1 v2"]);
+    }
+
+    #[test]
+    fn split_args_like_sh() {
+        assert_eq!(split_args("a  'b c' \"d\\\"e\\n\" f\\ g").unwrap(), ["a", "b c", "d\"e\\n", "f g"]);
+        assert_eq!(split_args("''").unwrap(), [""]);
+        assert!(split_args("'a").is_err());
+        assert!(split_args("\"a").is_err());
+        assert!(split_args("a\\").is_err());
+        assert!(split_args("  ").is_err());
+    }
+
+    #[test]
+    fn shards_count_from_one() {
+        let shard = parse_shard("2/5").unwrap();
+        assert_eq!((shard.index, shard.count), (2, 5));
+        assert!(parse_shard("0/5").is_err());
+        assert!(parse_shard("6/5").is_err());
+        assert!(parse_shard("2").is_err());
+    }
+
+    #[test]
+    fn longest_identifier_is_substituted() {
+        let names: Vec<String> = (1..=10).map(|i| format!("N{}", i)).collect();
+        let loaded_wordlist = wordlists(&names.iter().map(String::as_str).collect::<Vec<_>>(), 12);
+        let space = Space::combinations(12, 10).unwrap();
+        let template = precompute_template("N1 N10 N2", "", false, &loaded_wordlist);
+        assert_eq!(gen_command(&template, 0, &loaded_wordlist, &space, 1, false), "v0 v9 v1");
+    }
+
+    #[test]
+    fn ranges_include_both_ends() {
+        let range = parse_range("-2-7/3:N").unwrap();
+        assert_eq!((range.start, range.step, range.len, range.identifier.as_str()), (-2, 3, 4, "N"));
+        let range = parse_range("5-1:N").unwrap();
+        assert_eq!((range.start, range.step, range.len), (5, -1, 5));
+        assert!(parse_range("1-5").is_err());
+        assert!(parse_range("1-5/0:N").is_err());
+        assert!(parse_range("5:N").is_err());
+    }
+
+    #[test]
+    fn dates_take_an_identifier_and_a_format() {
+        let (dates, identifier) = parse_dates("2024-01-30..2024-02-02/day:D:%m%d").unwrap();
+        assert_eq!(identifier, "D");
+        assert_eq!((0..dates.len()).map(|line| dates.get(line)).collect::<Vec<_>>(), ["0130", "0131", "0201"]);
+        assert!(parse_dates("2024-01-30..2024-02-02").is_err());
+        assert!(parse_dates("2024-01-30..2024-02-02::%d").is_err());
+    }
+
+    #[test]
+    fn masks_take_an_identifier() {
+        let (mask, identifier) = parse_mask("?d?l:N").unwrap();
+        assert_eq!((mask.len(), identifier.as_str()), (260, "N"));
+        assert!(parse_mask("?d?l").is_err());
+        assert!(parse_mask("?d:").is_err());
+    }
+
+    #[test]
+    fn delimiters_understand_escapes() {
+        assert_eq!(parse_delimiter(""), Ok(Separator::Paragraph));
+        assert_eq!(parse_delimiter("\\0"), Ok(Separator::Bytes(vec![0])));
+        assert_eq!(parse_delimiter("::\\t\\x41\\\\"), Ok(Separator::Bytes(b"::\tA\\".to_vec())));
+        assert!(parse_delimiter("\\q").is_err());
+        assert!(parse_delimiter("\\xzz").is_err());
+        assert!(parse_delimiter("a\\").is_err());
+    }
+
+    #[test]
+    fn braces_only_substitute_whole_placeholders() {
+        let loaded_wordlist = wordlists(&["a"], 1);
+        let space = Space::product(&[vec![0]], &[1]).unwrap();
+        let template = precompute_template("a {a} ${a} {ab}", "", true, &loaded_wordlist);
+        assert_eq!(gen_command(&template, 0, &loaded_wordlist, &space, 1, false), "a v0 $v0 {ab}");
+    }
+
+    #[test]
+    fn double_braces_keep_the_identifier() {
+        let loaded_wordlist = wordlists(&["a"], 1);
+        let space = Space::product(&[vec![0]], &[1]).unwrap();
+        let template = precompute_template("{{a}} {a}", "", true, &loaded_wordlist);
+        assert_eq!(gen_command(&template, 0, &loaded_wordlist, &space, 1, false), "{a} v0");
+        let template = precompute_template("{{a}} a", "", false, &loaded_wordlist);
+        assert_eq!(gen_command(&template, 0, &loaded_wordlist, &space, 1, false), "a v0");
+    }
+
+    #[test]
+    fn modifiers_change_the_value() {
+        let loaded_wordlist = vec![("a".to_string(), Arc::new(Wordlist::Loaded(vec!["d/f.txt".to_string()])))];
+        let space = Space::product(&[vec![0]], &[1]).unwrap();
+        for braces in [false, true] {
+            let template = precompute_template("{a/} {a:basename} {a.} {a:ext} {a//} {a:upper}", "", braces, &loaded_wordlist);
+            assert_eq!(gen_command(&template, 0, &loaded_wordlist, &space, 1, false), "f.txt f.txt d/f txt d D/F.TXT");
+        }
     }
 }
@@ -0,0 +1,83 @@
+/// Every string matching a hashcat-style mask like `?l?l?d?d`, for --mask.
+#[derive(Debug, Clone)]
+pub struct Mask {
+    positions: Vec<Vec<char>>,
+    len: usize,
+}
+
+const SPECIAL: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+impl Mask {
+    /// `?l`, `?u`, `?d`, `?s`, `?a`, `?h` and `?H` stand for a class of characters, `??` for a `?` and anything else for itself.
+    pub fn parse(mask: &str) -> Result<Mask, String> {
+        let mut positions = vec![];
+        let mut chars = mask.chars();
+        while let Some(c) = chars.next() {
+            if c != '?' {
+                positions.push(vec![c]);
+                continue;
+            }
+            let class: String = match chars.next() {
+                Some('l') => ('a'..='z').collect(),
+                Some('u') => ('A'..='Z').collect(),
+                Some('d') => ('0'..='9').collect(),
+                Some('s') => SPECIAL.to_string(),
+                Some('a') => ('a'..='z').chain('A'..='Z').chain('0'..='9').chain(SPECIAL.chars()).collect(),
+                Some('h') => ('0'..='9').chain('a'..='f').collect(),
+                Some('H') => ('0'..='9').chain('A'..='F').collect(),
+                Some('?') => "?".to_string(),
+                Some(c) => return Err(format!("unknown character class ?{}, expected ?l, ?u, ?d, ?s, ?a, ?h, ?H or ??", c)),
+                None => return Err("the mask ends in a lone ?".to_string()),
+            };
+            positions.push(class.chars().collect());
+        }
+        let len = positions.iter().try_fold(1usize, |len, position| len.checked_mul(position.len()))
+            .ok_or(format!("{} matches more than {} strings", mask, usize::MAX))?;
+        Ok(Mask { positions, len })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The last position changes fastest, so the strings come in sorted order.
+    pub fn get(&self, line: usize) -> String {
+        let mut line = line;
+        let mut out: Vec<char> = self.positions.iter().rev().map(|position| {
+            let c = position[line % position.len()];
+            line /= position.len();
+            c
+        }).collect();
+        out.reverse();
+        out.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_come_in_sorted_order() {
+        let mask = Mask::parse("x?d?h").unwrap();
+        assert_eq!(mask.len(), 160);
+        assert_eq!([mask.get(0), mask.get(1), mask.get(16), mask.get(159)], ["x00", "x01", "x10", "x9f"]);
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(Mask::parse("?l").unwrap().get(25), "z");
+        assert_eq!(Mask::parse("?u").unwrap().get(0), "A");
+        assert_eq!(Mask::parse("?H").unwrap().get(15), "F");
+        assert_eq!(Mask::parse("?s").unwrap().len(), 33);
+        assert_eq!(Mask::parse("?a").unwrap().len(), 95);
+        assert_eq!(Mask::parse("a??").unwrap().get(0), "a?");
+    }
+
+    #[test]
+    fn invalid_masks() {
+        assert!(Mask::parse("?x").is_err());
+        assert!(Mask::parse("a?").is_err());
+        assert!(Mask::parse(&"?a".repeat(10)).is_err());
+    }
+}
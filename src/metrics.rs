@@ -0,0 +1,91 @@
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::State;
+
+const BUCKETS: [f64; 12] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+struct Histogram {
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+pub struct Metrics {
+    total: usize,
+    started: Instant,
+    durations: Mutex<Histogram>,
+}
+
+impl Metrics {
+    pub fn new(total: usize) -> Metrics {
+        Metrics {
+            total,
+            started: Instant::now(),
+            durations: Mutex::new(Histogram { counts: [0; BUCKETS.len()], count: 0, sum: 0.0 }),
+        }
+    }
+
+    pub fn observe(&self, runtime: Duration) {
+        let seconds = runtime.as_secs_f64();
+        let mut durations = self.durations.lock().unwrap();
+        for (count, bound) in durations.counts.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+        durations.count += 1;
+        durations.sum += seconds;
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    fn render(&self, state: &State) -> String {
+        let completed = state.completed.load(Ordering::SeqCst);
+        let running = state.running.lock().unwrap().len();
+        let rate = completed as f64 / self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = write!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value);
+        };
+        metric("parel_jobs_total", "gauge", "Number of jobs in this run.", self.total.to_string());
+        metric("parel_jobs_completed_total", "counter", "Number of finished jobs.", completed.to_string());
+        metric("parel_jobs_failed_total", "counter", "Number of failed jobs.", state.failed.load(Ordering::SeqCst).to_string());
+        metric("parel_jobs_skipped_total", "counter", "Number of skipped jobs.", state.skipped.load(Ordering::SeqCst).to_string());
+        metric("parel_jobs_retried_total", "counter", "Number of jobs that were retried.", state.retried.load(Ordering::SeqCst).to_string());
+        metric("parel_jobs_running", "gauge", "Number of jobs currently running.", running.to_string());
+        metric("parel_jobs_per_second", "gauge", "Average number of finished jobs per second.", format!("{:.3}", rate));
+
+        let durations = self.durations.lock().unwrap();
+        out.push_str("# HELP parel_job_duration_seconds Wall-clock duration of finished jobs.\n# TYPE parel_job_duration_seconds histogram\n");
+        for (count, bound) in durations.counts.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "parel_job_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+        }
+        let _ = writeln!(out, "parel_job_duration_seconds_bucket{{le=\"+Inf\"}} {}", durations.count);
+        let _ = writeln!(out, "parel_job_duration_seconds_sum {:.6}", durations.sum);
+        let _ = writeln!(out, "parel_job_duration_seconds_count {}", durations.count);
+        out
+    }
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics, state: &State) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+    let body = metrics.render(state);
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+}
+
+/// Binds `addr` and answers every HTTP request on it with the current metrics.
+pub fn serve(addr: &str, metrics: Arc<Metrics>, state: Arc<State>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = respond(stream, &metrics, &state);
+        }
+    });
+    Ok(())
+}
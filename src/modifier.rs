@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+
+/// A change to a value written after its identifier in braces, like `{file/}` or `{file:basename}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    /// The last component of a path.
+    Basename,
+    /// The path without the extension of its last component.
+    NoExt,
+    /// The extension of the last component of a path, without its dot.
+    Ext,
+    /// The directory a path is in.
+    Dir,
+    Upper,
+    Lower,
+}
+
+pub const MODIFIERS: &str = "basename, noext, ext, dir, upper, lower";
+
+impl Modifier {
+    /// The identifier and modifier of what is between braces, `None` if it has no modifier this knows.
+    pub fn split(token: &str) -> Option<(&str, Modifier)> {
+        if let Some(name) = token.strip_suffix("//") {
+            return Some((name, Modifier::Dir));
+        }
+        if let Some(name) = token.strip_suffix('/') {
+            return Some((name, Modifier::Basename));
+        }
+        if let Some(name) = token.strip_suffix('.') {
+            return Some((name, Modifier::NoExt));
+        }
+        let (name, modifier) = token.split_once(':')?;
+        let modifier = match modifier {
+            "basename" => Modifier::Basename,
+            "noext" => Modifier::NoExt,
+            "ext" => Modifier::Ext,
+            "dir" => Modifier::Dir,
+            "upper" => Modifier::Upper,
+            "lower" => Modifier::Lower,
+            _ => return None,
+        };
+        Some((name, modifier))
+    }
+
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self {
+            Modifier::Basename => Cow::Borrowed(basename(value)),
+            Modifier::NoExt => Cow::Borrowed(extension(value).map_or(value, |dot| &value[..dot])),
+            Modifier::Ext => Cow::Borrowed(extension(value).map_or("", |dot| &value[dot + 1..])),
+            Modifier::Dir => Cow::Borrowed(dirname(value)),
+            Modifier::Upper => Cow::Owned(value.to_uppercase()),
+            Modifier::Lower => Cow::Owned(value.to_lowercase()),
+        }
+    }
+}
+
+/// Where the dot of the extension of the last component of `path` is, a leading dot like in `.bashrc` isn't one.
+fn extension(path: &str) -> Option<usize> {
+    let start = path.rfind('/').map_or(0, |slash| slash + 1);
+    path[start..].rfind('.').filter(|&dot| dot > 0).map(|dot| start + dot)
+}
+
+/// What follows the last `/` like basename(1), trailing slashes left out.
+fn basename(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { "" } else { "/" };
+    }
+    trimmed.rsplit_once('/').map_or(trimmed, |(_, name)| name)
+}
+
+/// What comes before the last component of a path like dirname(1), `.` if there is nothing.
+fn dirname(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rsplit_once('/') {
+        Some((dir, _)) => {
+            let dir = dir.trim_end_matches('/');
+            if dir.is_empty() { "/" } else { dir }
+        }
+        None if trimmed.is_empty() && !path.is_empty() => "/",
+        None => ".",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(token: &str, value: &str) -> String {
+        let (name, modifier) = Modifier::split(token).unwrap();
+        assert_eq!(name, "f");
+        modifier.apply(value).into_owned()
+    }
+
+    #[test]
+    fn basename() {
+        assert_eq!(apply("f:basename", "dir/a/b.txt"), "b.txt");
+        assert_eq!(apply("f/", "b.txt"), "b.txt");
+        assert_eq!(apply("f/", "/x/y/"), "y");
+        assert_eq!(apply("f/", "/"), "/");
+    }
+
+    #[test]
+    fn noext_and_ext() {
+        assert_eq!(apply("f:noext", "dir/a/b.txt"), "dir/a/b");
+        assert_eq!(apply("f.", "c.tar.gz"), "c.tar");
+        assert_eq!(apply("f:ext", "c.tar.gz"), "gz");
+        assert_eq!(apply("f:noext", ".bashrc"), ".bashrc");
+        assert_eq!(apply("f:ext", ".bashrc"), "");
+        assert_eq!(apply("f:ext", "d.x/e"), "");
+    }
+
+    #[test]
+    fn dir() {
+        assert_eq!(apply("f:dir", "dir/a/b.txt"), "dir/a");
+        assert_eq!(apply("f//", "b.txt"), ".");
+        assert_eq!(apply("f//", "/x"), "/");
+        assert_eq!(apply("f//", "/x/y/"), "/x");
+        assert_eq!(apply("f//", "/"), "/");
+    }
+
+    #[test]
+    fn case() {
+        assert_eq!(apply("f:upper", "Wörld"), "WÖRLD");
+        assert_eq!(apply("f:lower", "Wörld"), "wörld");
+    }
+
+    #[test]
+    fn unknown_modifiers_are_not_split() {
+        assert_eq!(Modifier::split("f"), None);
+        assert_eq!(Modifier::split("f:-default"), None);
+    }
+}
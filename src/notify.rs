@@ -0,0 +1,35 @@
+use std::io::{self, Write};
+use colored::*;
+use serde_json::Value;
+use std::process::{Command, Stdio};
+
+/// Shows a desktop notification with notify-send, or osascript on macOS. Failures are ignored,
+/// since there's nothing useful to do about a missing notification daemon at the end of a run.
+pub fn desktop(title: &str, body: &str) {
+    let _ = if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title {:?}", body, title);
+        Command::new("osascript").arg("-e").arg(script).stdout(Stdio::null()).stderr(Stdio::null()).status()
+    }
+    else {
+        Command::new("notify-send").arg("--app-name=parel").arg(title).arg(body).stdout(Stdio::null()).stderr(Stdio::null()).status()
+    };
+}
+
+pub fn bell() {
+    let mut stderr = io::stderr().lock();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
+
+/// Asks the terminal to show `message` as a notification, which e.g. iTerm2, kitty and Windows Terminal support.
+pub fn osc9(message: &str) {
+    let mut stderr = io::stderr().lock();
+    let _ = write!(stderr, "\x1b]9;{}\x07", message);
+    let _ = stderr.flush();
+}
+
+pub fn webhook(url: &str, payload: &Value) {
+    if let Err(err) = ureq::post(url).content_type("application/json").send(payload.to_string()) {
+        eprintln!("{} Could not send webhook to '{}': {}", "warning:".yellow().bold(), url, err);
+    }
+}
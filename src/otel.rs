@@ -0,0 +1,160 @@
+use std::env;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use colored::*;
+use serde_json::{json, Value};
+use crate::JobResult;
+
+const BATCH_SIZE: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+fn random_id(bytes: usize) -> String {
+    (0..bytes.div_ceil(8))
+        .map(|_| format!("{:016x}", RandomState::new().hash_one(SystemTime::now())))
+        .collect::<String>()[..bytes * 2]
+        .to_string()
+}
+
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    let value = match value {
+        Value::Number(number) if number.is_i64() => json!({ "intValue": number.to_string() }),
+        Value::String(string) => json!({ "stringValue": string }),
+        value => json!({ "stringValue": value.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+/// Parses the `key=value,...` lists used by OTEL_EXPORTER_OTLP_HEADERS and OTEL_RESOURCE_ATTRIBUTES.
+fn env_pairs(name: &str) -> Vec<(String, String)> {
+    env::var(name).unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Exports one span per job, all children of a span covering the whole run, over OTLP/HTTP
+/// configured through the standard OTEL_* environment variables.
+pub struct Tracer {
+    trace_id: String,
+    root_id: String,
+    parent_id: Option<String>,
+    start: SystemTime,
+    spans: mpsc::Sender<Value>,
+    exporter: thread::JoinHandle<()>,
+}
+
+impl Tracer {
+    pub fn new() -> Tracer {
+        let endpoint = env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").unwrap_or_else(|_| {
+            let base = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").unwrap_or_else(|_| "http://localhost:4318".to_string());
+            format!("{}/v1/traces", base.trim_end_matches('/'))
+        });
+        let headers = env_pairs("OTEL_EXPORTER_OTLP_HEADERS");
+        let mut resource = vec![attribute("service.name", json!(env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "parel".to_string())))];
+        resource.extend(env_pairs("OTEL_RESOURCE_ATTRIBUTES").into_iter().map(|(key, value)| attribute(&key, json!(value))));
+
+        // Join the trace of whoever started parel when they pass a W3C TRACEPARENT.
+        let traceparent = env::var("TRACEPARENT").ok();
+        let parent = traceparent.as_deref().and_then(|traceparent| {
+            let fields: Vec<&str> = traceparent.split('-').collect();
+            (fields.len() == 4 && fields[1].len() == 32 && fields[2].len() == 16).then(|| (fields[1].to_string(), fields[2].to_string()))
+        });
+        let (trace_id, parent_id) = match parent {
+            Some((trace_id, parent_id)) => (trace_id, Some(parent_id)),
+            None => (random_id(16), None),
+        };
+
+        let (spans, received) = mpsc::channel::<Value>();
+        let exporter = thread::spawn(move || {
+            let mut batch = vec![];
+            let mut exported = Instant::now();
+            loop {
+                let done = match received.recv_timeout(EXPORT_INTERVAL) {
+                    Ok(span) => {
+                        batch.push(span);
+                        false
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => false,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => true,
+                };
+                if !batch.is_empty() && (done || batch.len() >= BATCH_SIZE || exported.elapsed() >= EXPORT_INTERVAL) {
+                    export(&endpoint, &headers, &resource, std::mem::take(&mut batch));
+                    exported = Instant::now();
+                }
+                if done {
+                    break;
+                }
+            }
+        });
+        Tracer { trace_id, root_id: random_id(8), parent_id, start: SystemTime::now(), spans, exporter }
+    }
+
+    pub fn record(&self, job: usize, result: &JobResult, command: &str, values: &[(&str, &str)]) {
+        let (exitval, signal) = result.status.exit_signal();
+        let mut attributes = vec![
+            attribute("parel.job", json!(job)),
+            attribute("parel.command", json!(command)),
+            attribute("parel.status", json!(result.status.to_string())),
+            attribute("parel.exit_code", json!(exitval)),
+            attribute("parel.signal", json!(signal)),
+        ];
+        attributes.extend(values.iter().map(|(identifier, value)| attribute(&format!("parel.value.{}", identifier), json!(value))));
+        let status = if result.status.success() { json!({ "code": 1 }) } else { json!({ "code": 2, "message": result.status.to_string() }) };
+        let _ = self.spans.send(json!({
+            "traceId": self.trace_id,
+            "spanId": random_id(8),
+            "parentSpanId": self.root_id,
+            "name": format!("job {}", job),
+            "kind": 1,
+            "startTimeUnixNano": nanos(result.start),
+            "endTimeUnixNano": nanos(result.start + result.runtime),
+            "attributes": attributes,
+            "status": status,
+        }));
+    }
+
+    /// Sends the span of the whole run and waits until every span has been exported.
+    pub fn finish(self, total: usize, failed: usize) {
+        let mut root = json!({
+            "traceId": self.trace_id,
+            "spanId": self.root_id,
+            "name": "parel",
+            "kind": 1,
+            "startTimeUnixNano": nanos(self.start),
+            "endTimeUnixNano": nanos(SystemTime::now()),
+            "attributes": [attribute("parel.jobs", json!(total)), attribute("parel.failed", json!(failed))],
+        });
+        if let Some(parent_id) = self.parent_id {
+            root["parentSpanId"] = json!(parent_id);
+        }
+        let _ = self.spans.send(root);
+        drop(self.spans);
+        let _ = self.exporter.join();
+    }
+}
+
+fn export(endpoint: &str, headers: &[(String, String)], resource: &[Value], spans: Vec<Value>) {
+    let body = json!({
+        "resourceSpans": [{
+            "resource": { "attributes": resource },
+            "scopeSpans": [{
+                "scope": { "name": "parel", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    });
+    let mut request = ureq::post(endpoint).content_type("application/json");
+    for (key, value) in headers {
+        request = request.header(key, value);
+    }
+    if let Err(err) = request.send(body.to_string()) {
+        eprintln!("{} Could not export spans to '{}': {}", "warning:".yellow().bold(), endpoint, err);
+    }
+}
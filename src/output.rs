@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use colored::*;
+use regex::bytes::Regex;
+use crate::JobStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    Message,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Ungrouped,
+    LineBuffer,
+    Group,
+    KeepOrder,
+}
+
+pub struct Options {
+    pub mode: Mode,
+    pub headers: bool,
+    pub failed_only: bool,
+    pub grep: Option<Regex>,
+    pub grep_v: Option<Regex>,
+    pub until_match: Option<Regex>,
+}
+
+/// How many messages workers can queue up before they wait for the collector.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Buffers stdout unless it is a terminal, and flushes it before anything goes to stderr so the two stay in order.
+struct Terminal {
+    stdout: io::BufWriter<io::Stdout>,
+    tty: bool,
+}
+
+impl Terminal {
+    fn print(&mut self, stream: Stream, chunk: &[u8]) {
+        match stream {
+            Stream::Stdout => {
+                let _ = self.stdout.write_all(chunk);
+                if self.tty {
+                    self.flush();
+                }
+            }
+            Stream::Stderr => {
+                self.flush();
+                eprint!("{}", String::from_utf8_lossy(chunk).red());
+            }
+            Stream::Message => {
+                self.flush();
+                let _ = io::stderr().lock().write_all(chunk);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = self.stdout.flush();
+    }
+}
+
+type Chunks = Vec<(Stream, Vec<u8>)>;
+
+enum Message {
+    Print(Stream, Vec<u8>),
+    Group(Chunks),
+    /// The output of the job with this dispatch order, for --keep-order.
+    Ordered(usize, Chunks),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Owns the terminal and prints what the workers send in the order it arrives, or in dispatch order for --keep-order.
+fn collect(messages: mpsc::Receiver<Message>, mut terminal: Terminal) {
+    let (mut next, mut done) = (0, BTreeMap::new());
+    loop {
+        let message = match messages.try_recv() {
+            Ok(message) => message,
+            Err(mpsc::TryRecvError::Empty) => {
+                // Nothing is waiting, so this is a good time to write out the buffer.
+                terminal.flush();
+                match messages.recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                }
+            }
+            Err(mpsc::TryRecvError::Disconnected) => break,
+        };
+        match message {
+            Message::Print(stream, chunk) => terminal.print(stream, &chunk),
+            Message::Group(chunks) => {
+                for (stream, chunk) in chunks {
+                    terminal.print(stream, &chunk);
+                }
+            }
+            Message::Ordered(seq, chunks) => {
+                done.insert(seq, chunks);
+                while let Some(chunks) = done.remove(&next) {
+                    for (stream, chunk) in chunks {
+                        terminal.print(stream, &chunk);
+                    }
+                    next += 1;
+                }
+            }
+            Message::Flush(flushed) => {
+                terminal.flush();
+                let _ = flushed.send(());
+            }
+        }
+    }
+    terminal.flush();
+}
+
+pub struct Printer {
+    options: Options,
+    messages: mpsc::SyncSender<Message>,
+    winner: AtomicUsize,
+}
+
+impl Printer {
+    pub fn new(options: Options) -> Printer {
+        let terminal = Terminal { stdout: io::BufWriter::with_capacity(1 << 16, io::stdout()), tty: io::stdout().is_terminal() };
+        let (messages, received) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        thread::spawn(move || collect(received, terminal));
+        Printer { options, messages, winner: AtomicUsize::new(usize::MAX) }
+    }
+
+    /// Whether any job printed a line matching --until-match.
+    pub fn found(&self) -> bool {
+        self.winner.load(Ordering::SeqCst) != usize::MAX
+    }
+
+    /// `seq` is the dispatch order of the job, which is the order --keep-order prints in.
+    pub fn job<'a>(&'a self, seq: usize, job: usize, command: &'a str, tag: Option<String>) -> JobOutput<'a> {
+        JobOutput {
+            printer: self,
+            seq,
+            job,
+            command,
+            tag,
+            streams: Mutex::new([StreamState::new(), StreamState::new()]),
+            buffer: Mutex::new(vec![]),
+        }
+    }
+
+    /// Waits until everything sent so far is written out, which has to happen before exiting.
+    pub fn flush(&self) {
+        let (flushed, done) = mpsc::channel();
+        if self.messages.send(Message::Flush(flushed)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+
+    fn send(&self, message: Message) {
+        let _ = self.messages.send(message);
+    }
+
+    fn print(&self, stream: Stream, chunk: &[u8]) {
+        self.send(Message::Print(stream, chunk.to_vec()));
+    }
+
+    fn filters(&self) -> bool {
+        self.options.grep.is_some() || self.options.grep_v.is_some() || self.options.until_match.is_some()
+    }
+
+    fn keep_line(&self, line: &[u8]) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        self.options.grep.as_ref().is_none_or(|grep| grep.is_match(line))
+            && !self.options.grep_v.as_ref().is_some_and(|grep_v| grep_v.is_match(line))
+    }
+}
+
+struct StreamState {
+    line_start: bool,
+    unfiltered: Vec<u8>,
+    partial: Vec<u8>,
+    matched: bool,
+}
+
+impl StreamState {
+    fn new() -> StreamState {
+        StreamState { line_start: true, unfiltered: vec![], partial: vec![], matched: false }
+    }
+}
+
+pub struct JobOutput<'a> {
+    printer: &'a Printer,
+    seq: usize,
+    job: usize,
+    command: &'a str,
+    tag: Option<String>,
+    streams: Mutex<[StreamState; 2]>,
+    buffer: Mutex<Chunks>,
+}
+
+impl JobOutput<'_> {
+    pub fn write(&self, stream: Stream, chunk: &[u8]) {
+        if stream == Stream::Message {
+            match self.printer.options.mode {
+                Mode::Group | Mode::KeepOrder => self.buffer.lock().unwrap().push((stream, chunk.to_vec())),
+                Mode::Ungrouped | Mode::LineBuffer => self.printer.print(stream, chunk),
+            }
+            return;
+        }
+        let mut streams = self.streams.lock().unwrap();
+        let state = &mut streams[if stream == Stream::Stdout { 0 } else { 1 }];
+        if !self.printer.filters() {
+            self.emit(state, stream, chunk);
+            return;
+        }
+        state.unfiltered.extend_from_slice(chunk);
+        let Some(end) = state.unfiltered.iter().rposition(|&b| b == b'\n') else {
+            return;
+        };
+        let lines: Vec<u8> = state.unfiltered.drain(..=end).collect();
+        self.filter(state, stream, &lines);
+    }
+
+    fn filter(&self, state: &mut StreamState, stream: Stream, lines: &[u8]) {
+        let mut kept = vec![];
+        for line in lines.split_inclusive(|&b| b == b'\n') {
+            self.find(line);
+            if self.printer.keep_line(line) {
+                kept.extend_from_slice(line);
+            }
+        }
+        if !kept.is_empty() {
+            state.matched = true;
+            self.emit(state, stream, &kept);
+        }
+    }
+
+    /// Makes this job the winner if `line` is the first to match --until-match.
+    fn find(&self, line: &[u8]) {
+        if let Some(ref until_match) = self.printer.options.until_match {
+            if until_match.is_match(line.strip_suffix(b"\n").unwrap_or(line)) {
+                let _ = self.printer.winner.compare_exchange(usize::MAX, self.seq, Ordering::SeqCst, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Looks for --until-match in a stream that isn't printed, `None` once the job closed it.
+    pub fn watch(&self, stream: Stream, chunk: Option<&[u8]>) {
+        if self.printer.options.until_match.is_none() {
+            return;
+        }
+        let mut streams = self.streams.lock().unwrap();
+        let state = &mut streams[if stream == Stream::Stdout { 0 } else { 1 }];
+        let lines: Vec<u8> = match chunk {
+            Some(chunk) => {
+                state.unfiltered.extend_from_slice(chunk);
+                let Some(end) = state.unfiltered.iter().rposition(|&b| b == b'\n') else {
+                    return;
+                };
+                state.unfiltered.drain(..=end).collect()
+            }
+            None => std::mem::take(&mut state.unfiltered),
+        };
+        for line in lines.split_inclusive(|&b| b == b'\n') {
+            self.find(line);
+        }
+    }
+
+    fn emit(&self, state: &mut StreamState, stream: Stream, chunk: &[u8]) {
+        let chunk = match self.tag {
+            Some(ref tag) => tag_lines(chunk, tag, &mut state.line_start),
+            None => chunk.to_vec(),
+        };
+        match self.printer.options.mode {
+            Mode::Ungrouped => self.printer.send(Message::Print(stream, chunk)),
+            Mode::LineBuffer => {
+                state.partial.extend_from_slice(&chunk);
+                if let Some(end) = state.partial.iter().rposition(|&b| b == b'\n') {
+                    let lines: Vec<u8> = state.partial.drain(..=end).collect();
+                    self.printer.print(stream, &lines);
+                }
+            }
+            Mode::Group | Mode::KeepOrder => self.buffer.lock().unwrap().push((stream, chunk)),
+        }
+    }
+
+    /// Flushes the unterminated last line of `stream` through the filters once the job closed it.
+    pub fn end(&self, stream: Stream) {
+        let mut streams = self.streams.lock().unwrap();
+        let state = &mut streams[if stream == Stream::Stdout { 0 } else { 1 }];
+        let lines = std::mem::take(&mut state.unfiltered);
+        if !lines.is_empty() {
+            self.filter(state, stream, &lines);
+        }
+    }
+
+    /// Whether this job is the one that first matched --until-match.
+    pub fn won(&self) -> bool {
+        self.printer.winner.load(Ordering::SeqCst) == self.seq
+    }
+
+    pub fn message(&self, message: String) {
+        self.write(Stream::Message, format!("{}\n", message).as_bytes());
+    }
+
+    /// Drops everything buffered so far, so only the last attempt of a retried job is printed.
+    pub fn clear(&self) {
+        if matches!(self.printer.options.mode, Mode::Group | Mode::KeepOrder) {
+            self.buffer.lock().unwrap().clear();
+            *self.streams.lock().unwrap() = [StreamState::new(), StreamState::new()];
+        }
+    }
+
+    /// `status` is `None` for jobs that were never run, which prints nothing in grouped modes.
+    pub fn finish(self, status: Option<JobStatus>) {
+        self.end(Stream::Stdout);
+        self.end(Stream::Stderr);
+        let matched = self.streams.lock().unwrap().iter().any(|state| state.matched);
+        let won = self.won();
+        let options = &self.printer.options;
+        let mut chunks = self.buffer.into_inner().unwrap();
+        match status {
+            Some(_) if options.until_match.is_some() && !won => chunks.clear(),
+            Some(_) if self.printer.filters() && !matched => chunks.clear(),
+            Some(status) if options.failed_only && status.success() => chunks.clear(),
+            Some(status) if options.headers => {
+                let status = if status.success() { status.to_string().green() } else { status.to_string().red() };
+                let header = format!("{} {} [{}]\n", format!("==> {}:", self.job).cyan().bold(), self.command, status);
+                chunks.insert(0, (Stream::Message, header.into_bytes()));
+            }
+            Some(_) => {}
+            None => chunks.clear(),
+        }
+        match options.mode {
+            Mode::Ungrouped => {}
+            Mode::LineBuffer => {
+                let [stdout, stderr] = self.streams.into_inner().unwrap();
+                for (stream, state) in [(Stream::Stdout, stdout), (Stream::Stderr, stderr)] {
+                    if !state.partial.is_empty() {
+                        self.printer.print(stream, &state.partial);
+                    }
+                }
+            }
+            Mode::Group => self.printer.send(Message::Group(chunks)),
+            Mode::KeepOrder => self.printer.send(Message::Ordered(self.seq, chunks)),
+        }
+    }
+}
+
+fn tag_lines(chunk: &[u8], tag: &str, line_start: &mut bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunk.len() + tag.len());
+    for line in chunk.split_inclusive(|&b| b == b'\n') {
+        if *line_start {
+            out.extend_from_slice(tag.as_bytes());
+        }
+        out.extend_from_slice(line);
+        *line_start = line.ends_with(b"\n");
+    }
+    out
+}
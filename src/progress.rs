@@ -0,0 +1,39 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::sync::atomic::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value};
+use crate::State;
+
+/// Counts, rate and ETA of the run, as written to --progress-file and sent to --webhook.
+pub fn status(state: &State, total: usize) -> Value {
+    // `finish` counts a job as completed before as failed, so reading in the other order never sees more failed jobs.
+    let failed = state.failed.load(Ordering::SeqCst);
+    let completed = state.completed.load(Ordering::SeqCst);
+    let elapsed = state.started.elapsed().as_secs_f64();
+    json!({
+        "total": total,
+        "completed": completed,
+        "succeeded": completed - failed,
+        "failed": failed,
+        "skipped": state.skipped.load(Ordering::SeqCst),
+        "retried": state.retried.load(Ordering::SeqCst),
+        "running": state.running.lock().unwrap().len(),
+        "threads": state.threads.load(Ordering::SeqCst),
+        "paused": state.paused.load(Ordering::SeqCst),
+        "elapsed": elapsed,
+        "rate": completed as f64 / elapsed.max(f64::EPSILON),
+        "eta": state.eta(total).map(|eta| eta.as_secs_f64()),
+        "updated": SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64(),
+    })
+}
+
+/// Rewrites the --progress-file through a temporary file, so readers never see a partial one.
+pub fn write(path: &str, state: &State, total: usize, finished: bool) -> io::Result<()> {
+    let mut status = status(state, total);
+    status["finished"] = json!(finished);
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp)?;
+    writeln!(file, "{}", status)?;
+    fs::rename(&tmp, path)
+}
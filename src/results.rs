@@ -0,0 +1,180 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use flate2::write::GzEncoder;
+use crate::output::Stream;
+use crate::JobResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+}
+
+pub enum Writer {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl Writer {
+    fn create(path: &Path, compression: Option<Compression>) -> io::Result<Writer> {
+        let file = File::create(path)?;
+        Ok(match compression {
+            None => Writer::Plain(file),
+            Some(Compression::Gzip) => Writer::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Some(Compression::Zstd) => Writer::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Writes the compression trailer, without which the file can't be decompressed.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Plain(mut file) => file.flush(),
+            Writer::Gzip(encoder) => encoder.finish().map(|_| ()),
+            Writer::Zstd(encoder) => encoder.finish().map(|_| ()),
+        }
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::Plain(file) => file.write(buf),
+            Writer::Gzip(encoder) => encoder.write(buf),
+            Writer::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Plain(file) => file.flush(),
+            Writer::Gzip(encoder) => encoder.flush(),
+            Writer::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+pub struct Results {
+    dir: PathBuf,
+    compression: Option<Compression>,
+}
+
+impl Results {
+    pub fn create(dir: PathBuf, compression: Option<Compression>) -> io::Result<Results> {
+        fs::create_dir_all(&dir)?;
+        Ok(Results { dir, compression })
+    }
+
+    /// Creates `DIR/<job>/` and truncates its output files, so a retried job only keeps its last attempt.
+    pub fn job(&self, job: usize) -> io::Result<JobFiles> {
+        let dir = self.dir.join(job.to_string());
+        fs::create_dir_all(&dir)?;
+        let extension = self.compression.map_or("", Compression::extension);
+        let stdout = Writer::create(&dir.join(format!("stdout{}", extension)), self.compression)?;
+        let stderr = Writer::create(&dir.join(format!("stderr{}", extension)), self.compression)?;
+        Ok(JobFiles { dir, stdout: Mutex::new(stdout), stderr: Mutex::new(stderr) })
+    }
+}
+
+pub struct JobFiles {
+    dir: PathBuf,
+    stdout: Mutex<Writer>,
+    stderr: Mutex<Writer>,
+}
+
+impl JobFiles {
+    pub fn write(&self, stream: Stream, chunk: &[u8]) -> io::Result<()> {
+        match stream {
+            Stream::Stdout => self.stdout.lock().unwrap().write_all(chunk),
+            Stream::Stderr => self.stderr.lock().unwrap().write_all(chunk),
+            Stream::Message => Ok(()),
+        }
+    }
+
+    /// Writes `exitcode` and `meta` next to the captured output.
+    pub fn finish(self, result: &JobResult, command: &str, values: &[(&str, &str)]) -> io::Result<()> {
+        self.stdout.into_inner().unwrap().finish()?;
+        self.stderr.into_inner().unwrap().finish()?;
+        let (exitval, signal) = result.status.exit_signal();
+        fs::write(self.dir.join("exitcode"), format!("{}\n", exitval))?;
+        let start = result.start.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let mut meta = format!("command={}\nstatus={}\nexitval={}\nsignal={}\nstart={:.3}\nruntime={:.3}\n",
+            command, result.status, exitval, signal, start, result.runtime.as_secs_f64());
+        for (identifier, value) in values {
+            meta.push_str(&format!("{}={}\n", identifier, value));
+        }
+        fs::write(self.dir.join("meta"), meta)
+    }
+}
+
+/// Everything a job's output is saved to besides the terminal.
+pub struct Capture {
+    files: Option<JobFiles>,
+    log: Option<Mutex<Writer>>,
+    buffer: Option<Mutex<[Vec<u8>; 2]>>,
+}
+
+impl Capture {
+    /// `buffer` keeps stdout and stderr in memory for --json and --sqlite.
+    pub fn new(files: Option<JobFiles>, log: Option<Writer>, buffer: bool) -> Capture {
+        Capture { files, log: log.map(Mutex::new), buffer: buffer.then(|| Mutex::new([vec![], vec![]])) }
+    }
+
+    pub fn active(&self) -> bool {
+        self.files.is_some() || self.log.is_some() || self.buffer.is_some()
+    }
+
+    pub fn take_output(&self) -> [Vec<u8>; 2] {
+        match self.buffer {
+            Some(ref buffer) => std::mem::take(&mut *buffer.lock().unwrap()),
+            None => [vec![], vec![]],
+        }
+    }
+
+    pub fn write(&self, stream: Stream, chunk: &[u8]) -> io::Result<()> {
+        if let Some(ref files) = self.files {
+            files.write(stream, chunk)?;
+        }
+        if let Some(ref buffer) = self.buffer {
+            match stream {
+                Stream::Stdout => buffer.lock().unwrap()[0].extend_from_slice(chunk),
+                Stream::Stderr => buffer.lock().unwrap()[1].extend_from_slice(chunk),
+                Stream::Message => {}
+            }
+        }
+        match self.log {
+            Some(ref log) if stream != Stream::Message => log.lock().unwrap().write_all(chunk),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn finish(self, result: &JobResult, command: &str, values: &[(&str, &str)]) -> io::Result<()> {
+        if let Some(log) = self.log {
+            log.into_inner().unwrap().finish()?;
+        }
+        match self.files {
+            Some(files) => files.finish(result, command, values),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Creates the --output-file of a job, along with any missing parent directories.
+pub fn create_log(path: &Path, compression: Option<Compression>) -> io::Result<Writer> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Writer::create(path, compression)
+}
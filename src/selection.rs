@@ -0,0 +1,208 @@
+/// Which job indices of the wordlists run and in which order, from --offset, --limit, --step, --shard, --reverse, --shuffle and --sample.
+#[derive(Debug, Clone, Copy)]
+pub struct Selection {
+    offset: usize,
+    len: usize,
+    step: usize,
+    reversed: bool,
+    permutation: Option<Permutation>,
+}
+
+impl Selection {
+    /// `limit` counts the indices after `offset`, of which every `step`th runs.
+    pub fn new(total: usize, offset: usize, limit: Option<usize>, step: usize) -> Selection {
+        let offset = offset.min(total);
+        let len = (total - offset).min(limit.unwrap_or(usize::MAX)).div_ceil(step);
+        Selection { offset, len, step, reversed: false, permutation: None }
+    }
+
+    /// Every `count`th of the selected jobs, starting at the `index`th.
+    pub fn shard(self, index: usize, count: usize) -> Selection {
+        Selection {
+            offset: self.offset + index * self.step,
+            len: self.len.saturating_sub(index).div_ceil(count),
+            step: self.step * count,
+            reversed: false,
+            permutation: None,
+        }
+    }
+
+    /// Runs the selected jobs from last to first.
+    pub fn reverse(self) -> Selection {
+        Selection { reversed: true, ..self }
+    }
+
+    /// Runs the selected jobs in a random order that only depends on `seed`.
+    pub fn shuffle(self, seed: u64) -> Selection {
+        Selection { permutation: Some(Permutation::new(self.len as u64, seed)), ..self }
+    }
+
+    /// Only runs the first `count` jobs of the shuffled order, a uniform random sample.
+    pub fn sample(self, count: usize, seed: u64) -> Selection {
+        Selection { len: self.len.min(count), ..self.shuffle(seed) }
+    }
+
+    /// How many jobs a --sample was taken from.
+    pub fn sampled_from(&self) -> Option<usize> {
+        self.permutation.map(|permutation| permutation.len as usize).filter(|&len| len != self.len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The job that runs as the `position`th.
+    pub fn get(&self, position: usize) -> Option<usize> {
+        if position >= self.len {
+            return None;
+        }
+        let position = if self.reversed { self.len - 1 - position } else { position };
+        let position = match self.permutation {
+            Some(ref permutation) => permutation.apply(position as u64) as usize,
+            None => position,
+        };
+        Some(self.offset + position * self.step)
+    }
+
+    pub fn contains(&self, job: usize) -> bool {
+        if job < self.offset || !(job - self.offset).is_multiple_of(self.step) {
+            return false;
+        }
+        let position = (job - self.offset) / self.step;
+        match self.permutation {
+            Some(ref permutation) => position < permutation.len as usize && (permutation.invert(position as u64) as usize) < self.len,
+            None => position < self.len,
+        }
+    }
+}
+
+const ROUNDS: usize = 4;
+
+/// A random permutation of `0..len` that needs no memory: a Feistel network over the next even power of two,
+/// walking the cycle until it lands back in range.
+#[derive(Debug, Clone, Copy)]
+struct Permutation {
+    len: u64,
+    half_bits: u32,
+    keys: [u64; ROUNDS],
+}
+
+impl Permutation {
+    fn new(len: u64, seed: u64) -> Permutation {
+        let bits = 64 - len.saturating_sub(1).leading_zeros();
+        let mut state = seed;
+        let keys = std::array::from_fn(|_| splitmix64(&mut state));
+        Permutation { len, half_bits: bits.div_ceil(2).max(1), keys }
+    }
+
+    fn apply(&self, position: u64) -> u64 {
+        let mut position = position;
+        loop {
+            position = self.encrypt(position);
+            if position < self.len {
+                return position;
+            }
+        }
+    }
+
+    fn invert(&self, position: u64) -> u64 {
+        let mut position = position;
+        loop {
+            position = self.decrypt(position);
+            if position < self.len {
+                return position;
+            }
+        }
+    }
+
+    fn encrypt(&self, value: u64) -> u64 {
+        let mask = (1u64 << self.half_bits) - 1;
+        let (mut left, mut right) = (value >> self.half_bits, value & mask);
+        for key in self.keys {
+            let mut state = right ^ key;
+            (left, right) = (right, left ^ (splitmix64(&mut state) & mask));
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn decrypt(&self, value: u64) -> u64 {
+        let mask = (1u64 << self.half_bits) - 1;
+        let (mut left, mut right) = (value >> self.half_bits, value & mask);
+        for key in self.keys.into_iter().rev() {
+            let mut state = left ^ key;
+            (left, right) = (right ^ (splitmix64(&mut state) & mask), left);
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// A seed for when --seed isn't given.
+pub fn random_seed() -> u64 {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    let mut state = nanos ^ ((std::process::id() as u64) << 32);
+    splitmix64(&mut state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutation_is_a_bijection() {
+        for len in [1, 2, 7, 100, 1000] {
+            let permutation = Permutation::new(len, 42);
+            let mut seen = vec![false; len as usize];
+            for position in 0..len {
+                let shuffled = permutation.apply(position);
+                assert!(shuffled < len);
+                assert!(!seen[shuffled as usize]);
+                seen[shuffled as usize] = true;
+                assert_eq!(permutation.invert(shuffled), position);
+            }
+        }
+    }
+
+    #[test]
+    fn step_offset_and_limit() {
+        let selection = Selection::new(10, 2, Some(5), 2);
+        assert_eq!(selection.len(), 3);
+        assert_eq!((0..3).map(|position| selection.get(position).unwrap()).collect::<Vec<_>>(), [2, 4, 6]);
+        assert!(selection.contains(4) && !selection.contains(5) && !selection.contains(8));
+    }
+
+    #[test]
+    fn reverse_runs_from_last_to_first() {
+        let selection = Selection::new(4, 0, None, 1).reverse();
+        assert_eq!((0..4).map(|position| selection.get(position).unwrap()).collect::<Vec<_>>(), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn shards_split_the_jobs() {
+        let mut jobs: Vec<usize> = (0..3).flat_map(|index| {
+            let shard = Selection::new(10, 0, None, 1).shard(index, 3);
+            (0..shard.len()).map(move |position| shard.get(position).unwrap())
+        }).collect();
+        jobs.sort();
+        assert_eq!(jobs, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sample_takes_distinct_selected_jobs() {
+        let selection = Selection::new(100, 0, None, 1).sample(10, 7);
+        assert_eq!(selection.len(), 10);
+        assert_eq!(selection.sampled_from(), Some(100));
+        let mut jobs: Vec<usize> = (0..10).map(|position| selection.get(position).unwrap()).collect();
+        assert!(jobs.iter().all(|&job| selection.contains(job)));
+        jobs.sort();
+        jobs.dedup();
+        assert_eq!(jobs.len(), 10);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use shell_quote::Sh;
+use crate::output::Stream;
+
+/// A long-lived `sh` that runs the jobs of one worker with `eval`, for --persistent-shell.
+pub struct Shell {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    stderr: ChildStderr,
+    sentinel: String,
+    dead: bool,
+}
+
+impl Shell {
+    pub fn spawn() -> io::Result<Shell> {
+        let mut child = Command::new("sh")
+            .process_group(0)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+        Ok(Shell {
+            stdin: child.stdin.take().unwrap(),
+            stdout: child.stdout.take().unwrap(),
+            stderr: child.stderr.take().unwrap(),
+            sentinel: format!("__parel_done_{}_{}_{}__", std::process::id(), child.id(), nanos),
+            child,
+            dead: false,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Whether the shell exited or was killed, after which it has to be replaced.
+    pub fn dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Runs `command` and waits for the sentinel its shell prints after it, like `wait_child`.
+    pub fn run(&mut self, command: &str, timeout: Option<Duration>, write: impl Fn(Stream, Option<&[u8]>) + Sync) -> io::Result<(ExitStatus, bool)> {
+        let mut script = b"eval ".to_vec();
+        script.extend(Sh::quote_vec(command));
+        script.extend(format!(" </dev/null\nprintf '{0}%d\\n' \"$?\"; printf '{0}\\n' >&2\n", self.sentinel).as_bytes());
+        if let Err(err) = self.stdin.write_all(&script).and_then(|_| self.stdin.flush()) {
+            self.dead = true;
+            return Err(err);
+        }
+        let sentinel = self.sentinel.as_bytes();
+        let pid = self.child.id();
+        let (done, finished) = mpsc::channel();
+        let (exit, timed_out) = thread::scope(|s| {
+            let (stdout, stderr) = (&mut self.stdout, &mut self.stderr);
+            let stdout_done = done.clone();
+            let write = &write;
+            let exit = s.spawn(move || {
+                let exit = read_until(stdout, sentinel, |chunk| write(Stream::Stdout, Some(chunk)));
+                write(Stream::Stdout, None);
+                let _ = stdout_done.send(());
+                exit
+            });
+            s.spawn(move || {
+                read_until(stderr, sentinel, |chunk| write(Stream::Stderr, Some(chunk)));
+                write(Stream::Stderr, None);
+                let _ = done.send(());
+            });
+            let deadline = timeout.map(|timeout| Instant::now() + timeout);
+            let mut timed_out = false;
+            for _ in 0..2 {
+                let received = match deadline {
+                    Some(deadline) => finished.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_ok(),
+                    None => finished.recv().is_ok(),
+                };
+                if !received && !timed_out {
+                    unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+                    timed_out = true;
+                    let _ = finished.recv();
+                }
+            }
+            (exit.join().unwrap_or(None), timed_out)
+        });
+        let code = exit.and_then(|exit| String::from_utf8_lossy(&exit).parse::<i32>().ok());
+        match code {
+            Some(code) if !timed_out => Ok((ExitStatus::from_raw(code << 8), false)),
+            _ => {
+                self.dead = true;
+                self.child.wait().map(|status| (status, timed_out))
+            }
+        }
+    }
+}
+
+impl Drop for Shell {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Forwards `reader` to `write` up to `sentinel` and returns the rest of its line, or `None` if the shell went away first.
+fn read_until(reader: &mut impl Read, sentinel: &[u8], mut write: impl FnMut(&[u8])) -> Option<Vec<u8>> {
+    let mut buf = [0u8; 8192];
+    let mut pending: Vec<u8> = vec![];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => 0,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => 0,
+        };
+        if n == 0 {
+            if !pending.is_empty() {
+                write(&pending);
+            }
+            return None;
+        }
+        pending.extend_from_slice(&buf[..n]);
+        if let Some(start) = pending.windows(sentinel.len()).position(|window| window == sentinel) {
+            if let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+                if start > 0 {
+                    write(&pending[..start]);
+                }
+                return Some(pending[start + sentinel.len()..start + end].to_vec());
+            }
+            continue;
+        }
+        // Holds back what could be the start of the sentinel.
+        let keep = pending.len().min(sentinel.len() - 1);
+        let ready: Vec<u8> = pending.drain(..pending.len() - keep).collect();
+        if !ready.is_empty() {
+            write(&ready);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Hands out its chunks one per read, like a pipe the shell writes to bit by bit.
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    fn read(chunks: Vec<&'static [u8]>, sentinel: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+        let mut written = vec![];
+        let rest = read_until(&mut Chunks(chunks), sentinel, |chunk| written.extend_from_slice(chunk));
+        (written, rest)
+    }
+
+    #[test]
+    fn sentinels_split_over_reads_are_found() {
+        assert_eq!(read(vec![b"out", b"put\n__SE", b"NT__", b"42\n"], b"__SENT__"), (b"output\n".to_vec(), Some(b"42".to_vec())));
+        assert_eq!(read(vec![b"__SENT__\n"], b"__SENT__"), (vec![], Some(vec![])));
+    }
+
+    #[test]
+    fn output_without_a_sentinel_is_kept() {
+        assert_eq!(read(vec![b"a__SE", b"b"], b"__SENT__"), (b"a__SEb".to_vec(), None));
+    }
+
+    #[test]
+    fn jobs_share_the_shell() {
+        let mut shell = Shell::spawn().unwrap();
+        let stdout = Mutex::new(vec![]);
+        let write = |stream: Stream, chunk: Option<&[u8]>| {
+            if let (Stream::Stdout, Some(chunk)) = (stream, chunk) {
+                stdout.lock().unwrap().extend_from_slice(chunk);
+            }
+        };
+        let (status, timed_out) = shell.run("x=1; printf a; (exit 3)", None, write).unwrap();
+        assert_eq!((status.code(), timed_out), (Some(3), false));
+        let (status, _) = shell.run("echo $x", None, write).unwrap();
+        assert!(status.success() && !shell.dead());
+        assert_eq!(stdout.into_inner().unwrap(), b"a1\n");
+    }
+}
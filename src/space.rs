@@ -0,0 +1,193 @@
+/// How a job number maps to a line of every wordlist: the product of the groups, where a group is a single wordlist,
+/// the columns of a CSV file or wordlists linked with --link that go in lockstep, or the identifiers of --combinations
+/// and --permutations.
+pub struct Space {
+    groups: Vec<Group>,
+    total: usize,
+}
+
+struct Group {
+    wordlists: Vec<usize>,
+    len: usize,
+    kind: Kind,
+}
+
+enum Kind {
+    /// The wordlists take the same line.
+    Lockstep,
+    /// The wordlists take different lines of the same `n`, in increasing order.
+    Combinations(usize),
+    /// The wordlists take different lines of the same `n`, in any order.
+    Permutations(usize),
+}
+
+impl Space {
+    /// Every combination of one line of each group of wordlists, `None` if there are more than fit in a usize.
+    /// The wordlists of a group take the same line, up to the end of the shortest.
+    pub fn product(groups: &[Vec<usize>], lengths: &[usize]) -> Option<Space> {
+        Space::new(groups.iter().map(|wordlists| Group {
+            len: wordlists.iter().map(|&i| lengths[i]).min().unwrap_or(1),
+            wordlists: wordlists.clone(),
+            kind: Kind::Lockstep,
+        }).collect())
+    }
+
+    /// Line i of every wordlist together, up to the end of the shortest.
+    pub fn linked(lengths: &[usize]) -> Space {
+        let len = lengths.iter().copied().min().unwrap_or(1);
+        let groups = if lengths.is_empty() { vec![] } else { vec![Group { wordlists: (0..lengths.len()).collect(), len, kind: Kind::Lockstep }] };
+        Space { groups, total: len }
+    }
+
+    /// Every set of `k` lines of a wordlist with `n` lines, without caring for their order.
+    pub fn combinations(n: usize, k: usize) -> Option<Space> {
+        let len = binomial(n, k)?;
+        Space::new(vec![Group { wordlists: (0..k).collect(), len, kind: Kind::Combinations(n) }])
+    }
+
+    /// Every ordering of `k` different lines of a wordlist with `n` lines.
+    pub fn permutations(n: usize, k: usize) -> Option<Space> {
+        let len = if k > n { 0 } else { (n - k + 1..=n).try_fold(1usize, |len, choices| len.checked_mul(choices))? };
+        Space::new(vec![Group { wordlists: (0..k).collect(), len, kind: Kind::Permutations(n) }])
+    }
+
+    fn new(groups: Vec<Group>) -> Option<Space> {
+        let total = groups.iter().try_fold(1usize, |total, group| total.checked_mul(group.len))?;
+        Some(Space { groups, total })
+    }
+
+    /// The number of combinations.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The line of every wordlist in the `nth` combination, the first wordlist changing fastest.
+    pub fn lines(&self, nth: usize) -> Vec<usize> {
+        let mut out = vec![0; self.groups.iter().map(|group| group.wordlists.len()).sum()];
+        let mut nth = nth;
+        for group in &self.groups {
+            let within = nth % group.len;
+            match group.kind {
+                Kind::Lockstep => for &wordlist in &group.wordlists {
+                    out[wordlist] = within;
+                },
+                Kind::Combinations(n) => {
+                    // Colexicographic order, so the first identifier changes fastest like in the product.
+                    let mut rank = within;
+                    let mut below = n;
+                    for (i, &wordlist) in group.wordlists.iter().enumerate().rev() {
+                        let fits = |line: usize| binomial(line, i + 1).is_some_and(|count| count <= rank);
+                        let (mut low, mut high) = (i, below);
+                        while high - low > 1 {
+                            let middle = low + (high - low) / 2;
+                            if fits(middle) {
+                                low = middle;
+                            }
+                            else {
+                                high = middle;
+                            }
+                        }
+                        rank -= binomial(low, i + 1).unwrap_or(0);
+                        out[wordlist] = low;
+                        below = low;
+                    }
+                }
+                Kind::Permutations(n) => {
+                    let mut rank = within;
+                    let mut taken: Vec<usize> = vec![];
+                    for (i, &wordlist) in group.wordlists.iter().enumerate() {
+                        // The rank picks among the lines no earlier identifier took.
+                        let mut line = rank % (n - i);
+                        rank /= n - i;
+                        for &other in &taken {
+                            if other <= line {
+                                line += 1;
+                            }
+                        }
+                        let at = taken.partition_point(|&other| other < line);
+                        taken.insert(at, line);
+                        out[wordlist] = line;
+                    }
+                }
+            }
+            nth /= group.len;
+        }
+        out
+    }
+}
+
+/// The number of ways to take `k` of `n`, `None` if it doesn't fit in a usize.
+fn binomial(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut count: u128 = 1;
+    for i in 0..k {
+        count = count * (n - i) as u128 / (i + 1) as u128;
+        if count > usize::MAX as u128 {
+            return None;
+        }
+    }
+    Some(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn product_changes_the_first_group_fastest() {
+        let space = Space::product(&[vec![0], vec![1, 2]], &[2, 3, 4]).unwrap();
+        assert_eq!(space.total(), 6);
+        assert_eq!(space.lines(0), [0, 0, 0]);
+        assert_eq!(space.lines(1), [1, 0, 0]);
+        assert_eq!(space.lines(2), [0, 1, 1]);
+        assert_eq!(space.lines(5), [1, 2, 2]);
+    }
+
+    #[test]
+    fn product_overflow_is_none() {
+        assert!(Space::product(&[vec![0], vec![1]], &[usize::MAX, 2]).is_none());
+    }
+
+    #[test]
+    fn linked_stops_at_the_shortest() {
+        let space = Space::linked(&[3, 5]);
+        assert_eq!(space.total(), 3);
+        assert_eq!(space.lines(2), [2, 2]);
+    }
+
+    #[test]
+    fn combinations_are_every_increasing_set_once() {
+        let space = Space::combinations(6, 3).unwrap();
+        assert_eq!(space.total(), 20);
+        assert_eq!(space.lines(0), [0, 1, 2]);
+        let sets: HashSet<Vec<usize>> = (0..space.total()).map(|nth| space.lines(nth)).collect();
+        assert_eq!(sets.len(), 20);
+        assert!(sets.iter().all(|lines| lines.windows(2).all(|pair| pair[0] < pair[1]) && lines[2] < 6));
+    }
+
+    #[test]
+    fn combinations_of_more_than_n_are_empty() {
+        assert_eq!(Space::combinations(2, 3).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn permutations_are_every_ordering_once() {
+        let space = Space::permutations(4, 2).unwrap();
+        assert_eq!(space.total(), 12);
+        let orderings: HashSet<Vec<usize>> = (0..space.total()).map(|nth| space.lines(nth)).collect();
+        assert_eq!(orderings.len(), 12);
+        assert!(orderings.iter().all(|lines| lines[0] != lines[1] && lines.iter().all(|&line| line < 4)));
+    }
+
+    #[test]
+    fn binomial_counts() {
+        assert_eq!(binomial(5, 0), Some(1));
+        assert_eq!(binomial(5, 2), Some(10));
+        assert_eq!(binomial(60, 3), Some(34220));
+        assert_eq!(binomial(3, 4), Some(0));
+    }
+}
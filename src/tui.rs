@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, Row, Sparkline, Table};
+use ratatui::{Frame, Terminal};
+use crate::State;
+
+const TICK: Duration = Duration::from_millis(250);
+const RATE_HISTORY: usize = 120;
+
+pub struct Tui {
+    done: Arc<AtomicBool>,
+    thread: thread::JoinHandle<io::Result<()>>,
+}
+
+impl Tui {
+    /// Takes over stderr with the dashboard until `finish` is called.
+    pub fn start(state: Arc<State>, total: usize) -> io::Result<Tui> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+        let done = Arc::new(AtomicBool::new(false));
+        let finished = done.clone();
+        let thread = thread::spawn(move || {
+            let mut dashboard = Dashboard { state, total, rates: VecDeque::new(), last: (Instant::now(), 0) };
+            while !finished.load(Ordering::SeqCst) {
+                dashboard.sample();
+                terminal.draw(|frame| dashboard.draw(frame))?;
+                if event::poll(TICK)? {
+                    if let Event::Key(key) = event::read()? {
+                        if key.kind == KeyEventKind::Press {
+                            dashboard.key(key.code, key.modifiers);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        });
+        Ok(Tui { done, thread })
+    }
+
+    pub fn finish(self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Ok(Err(err)) = self.thread.join() {
+            let _ = restore();
+            eprintln!("tui: {}", err);
+            return;
+        }
+        let _ = restore();
+    }
+}
+
+fn restore() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen, ratatui::crossterm::cursor::Show)
+}
+
+struct Dashboard {
+    state: Arc<State>,
+    total: usize,
+    /// Jobs finished per second, newest last.
+    rates: VecDeque<u64>,
+    last: (Instant, usize),
+}
+
+impl Dashboard {
+    fn sample(&mut self) {
+        let (at, completed) = self.last;
+        if at.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        let now = self.state.completed.load(Ordering::SeqCst);
+        self.rates.push_back((now - completed) as u64);
+        if self.rates.len() > RATE_HISTORY {
+            self.rates.pop_front();
+        }
+        self.last = (Instant::now(), now);
+    }
+
+    fn key(&self, code: KeyCode, modifiers: KeyModifiers) {
+        let state = &self.state;
+        match code {
+            KeyCode::Char('p') => {
+                state.paused.fetch_xor(true, Ordering::SeqCst);
+            }
+            KeyCode::Char('+') => {
+                state.threads.fetch_add(1, Ordering::SeqCst);
+            }
+            KeyCode::Char('-') => {
+                let _ = state.threads.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |threads| (threads > 1).then(|| threads - 1));
+            }
+            KeyCode::Char('a') => state.abort(),
+            KeyCode::Char('q') => {
+                let _ = state.interrupted.compare_exchange(0, libc::SIGINT, Ordering::SeqCst, Ordering::SeqCst);
+            }
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                let _ = state.interrupted.compare_exchange(0, libc::SIGINT, Ordering::SeqCst, Ordering::SeqCst);
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let state = &self.state;
+        let [progress, jobs, rate, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(6),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+        let [running_area, failures_area] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(jobs);
+
+        let completed = state.completed.load(Ordering::SeqCst) + state.skipped.load(Ordering::SeqCst);
+        let failed = state.failed.load(Ordering::SeqCst);
+        let eta = match state.eta(self.total) {
+            Some(eta) => indicatif::HumanDuration(eta).to_string(),
+            None => "unknown".to_string(),
+        };
+        let mut title = format!(" {}/{} jobs, {} failed, ETA {}, {} threads ", completed, self.total, failed, eta, state.threads.load(Ordering::SeqCst));
+        if state.paused.load(Ordering::SeqCst) {
+            title.push_str("[paused] ");
+        }
+        let ratio = if self.total == 0 { 1.0 } else { (completed as f64 / self.total as f64).min(1.0) };
+        frame.render_widget(Gauge::default().block(Block::bordered().title(title)).gauge_style(Style::new().fg(Color::Cyan)).ratio(ratio), progress);
+
+        let running = state.running.lock().unwrap();
+        let mut jobs: Vec<_> = running.iter().collect();
+        jobs.sort_unstable_by_key(|&(&job, _)| job);
+        let rows = jobs.into_iter().map(|(job, running)| {
+            Row::new([job.to_string(), format!("{:.1}s", running.started.elapsed().as_secs_f64()), running.command.clone()])
+        });
+        let table = Table::new(rows, [Constraint::Length(8), Constraint::Length(8), Constraint::Fill(1)])
+            .header(Row::new(["Job", "Time", "Command"]).bold())
+            .block(Block::bordered().title(format!(" Running ({}) ", running.len())));
+        frame.render_widget(table, running_area);
+        drop(running);
+
+        let failures = state.failures.lock().unwrap();
+        let items = failures.iter().rev().map(|(job, status, command)| Line::from(format!("[{}] {}: {}", job, status, command)).red());
+        frame.render_widget(List::new(items).block(Block::bordered().title(format!(" Failed ({}) ", failed))), failures_area);
+        drop(failures);
+
+        let width = rate.width.saturating_sub(2) as usize;
+        let rates: Vec<u64> = self.rates.iter().skip(self.rates.len().saturating_sub(width)).copied().collect();
+        let current = rates.last().copied().unwrap_or(0);
+        let sparkline = Sparkline::default()
+            .block(Block::bordered().title(format!(" Throughput ({} jobs/s) ", current)))
+            .data(&rates)
+            .style(Style::new().fg(Color::Green));
+        frame.render_widget(sparkline, rate);
+
+        frame.render_widget(Line::from(" p pause/resume   + - threads   q stop after running jobs   a abort").dim(), help);
+    }
+}
@@ -0,0 +1,551 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use memmap2::Mmap;
+use crate::dates::Dates;
+use crate::mask::Mask;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+/// The lines of a -f file, or its entries ended by another --delimiter.
+pub enum Wordlist {
+    Loaded(Vec<String>),
+    /// The file mapped into memory with where each line starts and what ends it, for --mmap.
+    Mapped(Mmap, Vec<usize>, Format),
+    Streamed(Streamed),
+    /// Several files given for the same identifier, one after the other.
+    Concatenated(Vec<Wordlist>),
+    /// Numbers counting from `start` by `step`, for --range.
+    Range { start: i64, step: i64, len: usize },
+    Dates(Dates),
+    Mask(Mask),
+}
+
+/// What ends an entry of a wordlist file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Separator {
+    Bytes(Vec<u8>),
+    /// One or more blank lines, so every entry is a paragraph.
+    Paragraph,
+}
+
+impl Separator {
+    pub fn newline() -> Separator {
+        Separator::Bytes(b"\n".to_vec())
+    }
+
+    /// Where the first entry of `data` is and how much of `data` it takes with its separator, `None` if `data` can't
+    /// tell yet because it doesn't end with the file.
+    fn next(&self, data: &[u8], eof: bool) -> Option<(Range<usize>, usize)> {
+        match self {
+            Separator::Bytes(separator) => {
+                let end = if let [byte] = separator.as_slice() {
+                    data.iter().position(|b| b == byte)
+                }
+                else {
+                    data.windows(separator.len()).position(|window| window == separator.as_slice())
+                };
+                match end {
+                    Some(end) => Some((0..end, end + separator.len())),
+                    None if eof && !data.is_empty() => Some((0..data.len(), data.len())),
+                    None => None,
+                }
+            }
+            Separator::Paragraph => {
+                let start = data.iter().position(|&b| b != b'\n' && b != b'\r')?;
+                let blank = (start..data.len()).find(|&i| data[i] == b'\n' && matches!(data.get(i + 1..), Some([b'\n', ..] | [b'\r', b'\n', ..])));
+                match blank {
+                    Some(end) => Some((start..end, end + 1)),
+                    None if eof => {
+                        let end = data.iter().rposition(|&b| b != b'\n' && b != b'\r').map_or(start, |end| end + 1);
+                        Some((start..end, data.len()))
+                    }
+                    None => None,
+                }
+            }
+        }
+    }
+
+    /// Drops the `\r` of a CRLF line like `BufRead::lines`.
+    fn trim<'a>(&self, entry: &'a [u8]) -> &'a [u8] {
+        if *self == Separator::newline() || *self == Separator::Paragraph {
+            entry.strip_suffix(b"\r").unwrap_or(entry)
+        }
+        else {
+            entry
+        }
+    }
+}
+
+/// How the entries of wordlist files are read.
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub separator: Separator,
+    /// Leaves out entries that are empty or only whitespace, for --skip-empty.
+    pub skip_empty: bool,
+    pub comment_prefix: Option<String>,
+    /// Strips whitespace around every entry, for --trim.
+    pub trim: bool,
+    /// Keeps entries that aren't valid UTF-8 with the invalid bytes replaced by U+FFFD, for --lossy.
+    pub lossy: bool,
+    /// How many entries weren't valid UTF-8 since it was last taken.
+    pub invalid: Arc<AtomicUsize>,
+}
+
+impl Format {
+    fn trim<'a>(&self, entry: &'a str) -> &'a str {
+        if self.trim { entry.trim() } else { entry }
+    }
+
+    /// The entry as text, `None` if it isn't valid UTF-8 and is left out.
+    fn decode<'a>(&self, entry: &'a [u8]) -> Option<Cow<'a, str>> {
+        match std::str::from_utf8(entry) {
+            Ok(entry) => Some(Cow::Borrowed(entry)),
+            Err(_) => {
+                self.invalid.fetch_add(1, Ordering::Relaxed);
+                self.lossy.then(|| String::from_utf8_lossy(entry))
+            }
+        }
+    }
+
+    /// How many entries weren't valid UTF-8 since the last call.
+    pub fn take_invalid(&self) -> usize {
+        self.invalid.swap(0, Ordering::Relaxed)
+    }
+
+    fn keeps(&self, entry: &str) -> bool {
+        let start = entry.trim_start();
+        let blank = self.skip_empty && start.is_empty();
+        let comment = self.comment_prefix.as_ref().is_some_and(|prefix| start.starts_with(prefix.as_str()));
+        !blank && !comment
+    }
+}
+
+impl Wordlist {
+    pub fn load<P: AsRef<Path>>(path: P, format: &Format) -> io::Result<Wordlist> {
+        lines(path.as_ref(), format)?.collect::<io::Result<_>>().map(Wordlist::Loaded)
+    }
+
+    /// The first `count` columns of a CSV file, or a tab separated one if it ends in .tsv, missing ones left empty.
+    /// Rows end at newlines or at a --delimiter of one byte, --trim trims every field.
+    pub fn columns<P: AsRef<Path>>(path: P, count: usize, format: &Format) -> io::Result<Vec<Wordlist>> {
+        let path = path.as_ref();
+        let reader: Box<dyn io::Read> = if is_stdin(path) { Box::new(io::stdin()) } else { Box::new(File::open(path)?) };
+        let mut builder = csv::ReaderBuilder::new();
+        builder.has_headers(false).flexible(true).trim(if format.trim { csv::Trim::All } else { csv::Trim::None });
+        if path.extension().is_some_and(|extension| extension == "tsv") {
+            builder.delimiter(b'\t');
+        }
+        if let Separator::Bytes(separator) = &format.separator {
+            if let [byte] = separator.as_slice() {
+                if *byte != b'\n' {
+                    builder.terminator(csv::Terminator::Any(*byte));
+                }
+            }
+        }
+        let mut columns = vec![vec![]; count];
+        for record in builder.from_reader(reader).byte_records() {
+            let record = record?;
+            let Some(fields) = record.iter().map(|field| format.decode(field)).collect::<Option<Vec<_>>>() else {
+                continue;
+            };
+            let first = fields.first().map_or("", |field| field.trim_start());
+            let blank = format.skip_empty && fields.iter().all(|field| field.trim().is_empty());
+            let comment = format.comment_prefix.as_ref().is_some_and(|prefix| first.starts_with(prefix.as_str()));
+            if blank || comment {
+                continue;
+            }
+            let mut fields = fields.into_iter();
+            for column in &mut columns {
+                column.push(fields.next().map(Cow::into_owned).unwrap_or_default());
+            }
+        }
+        Ok(columns.into_iter().map(Wordlist::Loaded).collect())
+    }
+
+    /// The lines `command` prints, for --file-cmd.
+    pub fn command(command: &str, format: &Format) -> io::Result<Wordlist> {
+        let output = Command::new("sh").arg("-c").arg(command).stdin(Stdio::null()).stderr(Stdio::inherit()).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("it exited with {}", output.status)));
+        }
+        valid_lines(output.stdout.as_slice(), format).collect::<io::Result<_>>().map(Wordlist::Loaded)
+    }
+
+    /// The paths matching `pattern` in sorted order, for --glob.
+    pub fn glob(pattern: &str) -> Result<Wordlist, glob::PatternError> {
+        let paths = glob::glob(pattern)?.filter_map(Result::ok).filter_map(|path| path.to_str().map(str::to_string));
+        Ok(Wordlist::Loaded(paths.collect()))
+    }
+
+    /// Every column of a CSV file with a header row, named after its header, for --csv.
+    pub fn csv<P: AsRef<Path>>(path: P) -> io::Result<Vec<(String, Wordlist)>> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+        let headers: Vec<String> = reader.headers()?.iter().map(str::to_string).collect();
+        let mut columns = vec![vec![]; headers.len()];
+        for record in reader.records() {
+            let record = record?;
+            for (i, column) in columns.iter_mut().enumerate() {
+                column.push(record.get(i).unwrap_or_default().to_string());
+            }
+        }
+        Ok(headers.into_iter().zip(columns.into_iter().map(Wordlist::Loaded)).collect())
+    }
+
+    /// Every column of the result of `query` on an SQLite database, named like in the query, for --sql.
+    pub fn sql<P: AsRef<Path>>(path: P, query: &str) -> rusqlite::Result<Vec<(String, Wordlist)>> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut statement = connection.prepare(query)?;
+        let names: Vec<String> = statement.column_names().into_iter().map(str::to_string).collect();
+        let mut columns = vec![vec![]; names.len()];
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            for (i, column) in columns.iter_mut().enumerate() {
+                column.push(match row.get_ref(i)? {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(value) => value.to_string(),
+                    ValueRef::Real(value) => value.to_string(),
+                    ValueRef::Text(value) | ValueRef::Blob(value) => String::from_utf8_lossy(value).into_owned(),
+                });
+            }
+        }
+        Ok(names.into_iter().zip(columns.into_iter().map(Wordlist::Loaded)).collect())
+    }
+
+    /// Only remembers the offset of every line, the mapping is read by `get` when a job needs it.
+    pub fn map<P: AsRef<Path>>(path: P, format: &Format) -> io::Result<Wordlist> {
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+        let mut offsets = vec![];
+        let mut start = 0;
+        let separator = &format.separator;
+        while let Some((entry, taken)) = separator.next(&map[start..], true) {
+            let line = separator.trim(&map[start + entry.start..start + entry.end]);
+            if format.decode(line).is_some_and(|line| format.keeps(&line)) {
+                offsets.push(start + entry.start);
+            }
+            start += taken;
+        }
+        Ok(Wordlist::Mapped(map, offsets, format.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Wordlist::Loaded(lines) => lines.len(),
+            Wordlist::Mapped(_, offsets, _) => offsets.len(),
+            Wordlist::Streamed(streamed) => streamed.len,
+            Wordlist::Concatenated(parts) => parts.iter().map(Wordlist::len).sum(),
+            Wordlist::Range { len, .. } => *len,
+            Wordlist::Dates(dates) => dates.len(),
+            Wordlist::Mask(mask) => mask.len(),
+        }
+    }
+
+    pub fn get(&self, line: usize) -> Cow<'_, str> {
+        match self {
+            Wordlist::Loaded(lines) => Cow::Borrowed(&lines[line]),
+            Wordlist::Mapped(map, offsets, format) => {
+                let rest = &map[offsets[line]..];
+                let line = format.separator.next(rest, true).map_or(&rest[..0], |(entry, _)| &rest[entry]);
+                let line = format.separator.trim(line);
+                match String::from_utf8_lossy(line) {
+                    Cow::Borrowed(line) => Cow::Borrowed(format.trim(line)),
+                    Cow::Owned(line) => Cow::Owned(format.trim(&line).to_string()),
+                }
+            }
+            Wordlist::Streamed(streamed) => Cow::Owned(streamed.get(line)),
+            Wordlist::Concatenated(parts) => {
+                let mut line = line;
+                for part in parts {
+                    if line < part.len() {
+                        return part.get(line);
+                    }
+                    line -= part.len();
+                }
+                Cow::Borrowed("")
+            }
+            Wordlist::Range { start, step, .. } => Cow::Owned((*start as i128 + *step as i128 * line as i128).to_string()),
+            Wordlist::Dates(dates) => Cow::Owned(dates.get(line)),
+            Wordlist::Mask(mask) => Cow::Owned(mask.get(line)),
+        }
+    }
+
+    /// Only the lines where `keep` is true.
+    fn retain(self, keep: &[bool]) -> Wordlist {
+        match self {
+            Wordlist::Loaded(lines) => Wordlist::Loaded(lines.into_iter().zip(keep).filter(|(_, &keep)| keep).map(|(line, _)| line).collect()),
+            Wordlist::Mapped(map, offsets, format) => {
+                let offsets = offsets.into_iter().zip(keep).filter(|(_, &keep)| keep).map(|(offset, _)| offset).collect();
+                Wordlist::Mapped(map, offsets, format)
+            }
+            other => Wordlist::Loaded((0..other.len()).filter(|&line| keep[line]).map(|line| other.get(line).into_owned()).collect()),
+        }
+    }
+
+    /// The lines in the order of `order`, which holds the line coming at each position.
+    fn reorder(self, order: &[usize]) -> Wordlist {
+        match self {
+            Wordlist::Loaded(mut lines) => Wordlist::Loaded(order.iter().map(|&line| std::mem::take(&mut lines[line])).collect()),
+            Wordlist::Mapped(map, offsets, format) => Wordlist::Mapped(map, order.iter().map(|&line| offsets[line]).collect(), format),
+            other => Wordlist::Loaded(order.iter().map(|&line| other.get(line).into_owned()).collect()),
+        }
+    }
+
+    /// Forgets a line of a streamed wordlist once its job finished.
+    pub fn release(&self, line: usize) {
+        match self {
+            Wordlist::Streamed(streamed) => {
+                streamed.reader.lock().unwrap().window.remove(&line);
+            }
+            Wordlist::Concatenated(parts) => {
+                let mut line = line;
+                for part in parts {
+                    if line < part.len() {
+                        return part.release(line);
+                    }
+                    line -= part.len();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Drops every row of `columns` that is the same as one before it for --unique, and returns how many it dropped.
+pub fn unique(columns: Vec<Wordlist>) -> (Vec<Wordlist>, usize) {
+    let len = columns.iter().map(Wordlist::len).min().unwrap_or(0);
+    let keep: Vec<bool> = if let [column] = columns.as_slice() {
+        let mut seen = HashSet::with_capacity(len);
+        (0..len).map(|row| seen.insert(column.get(row))).collect()
+    }
+    else {
+        let mut seen = HashSet::with_capacity(len);
+        (0..len).map(|row| seen.insert(columns.iter().map(|column| column.get(row)).collect::<Vec<_>>())).collect()
+    };
+    let removed = keep.iter().filter(|&&keep| !keep).count();
+    if removed == 0 {
+        return (columns, 0);
+    }
+    (columns.into_iter().map(|column| column.retain(&keep)).collect(), removed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sort {
+    Asc,
+    Desc,
+    /// By the number at the start of each line, lines without one after all others.
+    Numeric,
+}
+
+/// Sorts the rows of `columns` for --sort, by the first column and then the next ones.
+pub fn sort(columns: Vec<Wordlist>, sort: Sort) -> Vec<Wordlist> {
+    let len = columns.iter().map(Wordlist::len).min().unwrap_or(0);
+    let mut order: Vec<usize> = (0..len).collect();
+    {
+        let rows: Vec<Vec<Cow<str>>> = (0..len).map(|row| columns.iter().map(|column| column.get(row)).collect()).collect();
+        match sort {
+            Sort::Asc => order.sort_by(|&a, &b| rows[a].cmp(&rows[b])),
+            Sort::Desc => order.sort_by(|&a, &b| rows[b].cmp(&rows[a])),
+            Sort::Numeric => {
+                let numbers: Vec<Option<f64>> = rows.iter().map(|row| leading_number(&row[0])).collect();
+                order.sort_by(|&a, &b| match (numbers[a], numbers[b]) {
+                    (Some(x), Some(y)) => x.total_cmp(&y).then_with(|| rows[a].cmp(&rows[b])),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => rows[a].cmp(&rows[b]),
+                });
+            }
+        }
+    }
+    columns.into_iter().map(|column| column.reorder(&order)).collect()
+}
+
+/// The longest number at the start of `line` after whitespace, like `sort -n`.
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let end = line.find(|c: char| !matches!(c, '0'..='9' | '+' | '-' | '.' | 'e' | 'E')).unwrap_or(line.len());
+    (1..=end).rev().find_map(|end| line[..end].parse::<f64>().ok())
+}
+
+/// Reads a wordlist as jobs ask for its lines for --stream, only keeping the lines of jobs that haven't finished.
+pub struct Streamed {
+    len: usize,
+    reader: Mutex<Reader>,
+    wanted: Box<dyn Fn(usize) -> bool + Send + Sync>,
+}
+
+struct Reader {
+    lines: Box<dyn Iterator<Item = io::Result<String>> + Send>,
+    next: usize,
+    window: HashMap<usize, String>,
+}
+
+impl Streamed {
+    /// Counts the lines up front, so the number of jobs is known without keeping them.
+    pub fn open<P: AsRef<Path>>(path: P, format: &Format) -> io::Result<Streamed> {
+        let mut len = 0;
+        let path = path.as_ref();
+        for line in lines(path, format)? {
+            line?;
+            len += 1;
+        }
+        let reader = Reader { lines: Box::new(lines(path, format)?), next: 0, window: HashMap::new() };
+        Ok(Streamed { len, reader: Mutex::new(reader), wanted: Box::new(|_| true) })
+    }
+
+    /// Only lines passing `wanted` are kept when reading ahead, the others belong to jobs that never run.
+    pub fn keep(&mut self, wanted: impl Fn(usize) -> bool + Send + Sync + 'static) {
+        self.wanted = Box::new(wanted);
+    }
+
+    /// Jobs are claimed in order, so reading ahead only keeps the lines of jobs that are about to ask for them.
+    fn get(&self, line: usize) -> String {
+        let mut reader = self.reader.lock().unwrap();
+        while reader.next <= line {
+            let Some(Ok(value)) = reader.lines.next() else {
+                break;
+            };
+            let next = reader.next;
+            if next == line || (self.wanted)(next) {
+                reader.window.insert(next, value);
+            }
+            reader.next += 1;
+        }
+        reader.window.get(&line).cloned().unwrap_or_default()
+    }
+}
+
+/// The lines of `path`, or of stdin for `-`, leaving out those that aren't valid UTF-8 unless --lossy.
+fn lines(path: &Path, format: &Format) -> io::Result<impl Iterator<Item = io::Result<String>> + Send> {
+    let reader: Box<dyn BufRead + Send> = if is_stdin(path) {
+        Box::new(io::BufReader::new(io::stdin()))
+    }
+    else {
+        Box::new(io::BufReader::new(File::open(path)?))
+    };
+    Ok(valid_lines(reader, format))
+}
+
+fn valid_lines(reader: impl BufRead + Send, format: &Format) -> impl Iterator<Item = io::Result<String>> + Send {
+    let entries = Entries { reader, separator: format.separator.clone(), buf: vec![], start: 0, eof: false };
+    let format = format.clone();
+    entries.filter_map(move |entry| match entry {
+        Ok(entry) => String::from_utf8(entry).map_or_else(|err| format.decode(err.as_bytes()).map(Cow::into_owned), Some)
+            .map(|line| if format.trim { line.trim().to_string() } else { line })
+            .filter(|line| format.keeps(line)).map(Ok),
+        Err(err) => Some(Err(err)),
+    })
+}
+
+/// The entries of a reader, only reading as far as the next one ends.
+struct Entries<R> {
+    reader: R,
+    separator: Separator,
+    buf: Vec<u8>,
+    start: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> Iterator for Entries<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        loop {
+            if let Some((entry, taken)) = self.separator.next(&self.buf[self.start..], self.eof) {
+                let data = &self.buf[self.start..];
+                let entry = self.separator.trim(&data[entry]).to_vec();
+                self.start += taken;
+                return Some(Ok(entry));
+            }
+            if self.eof {
+                return None;
+            }
+            self.buf.drain(..self.start);
+            self.start = 0;
+            let read = match self.reader.fill_buf() {
+                Ok(chunk) => {
+                    self.buf.extend_from_slice(chunk);
+                    chunk.len()
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+            self.reader.consume(read);
+            self.eof = read == 0;
+        }
+    }
+}
+
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(separator: Separator) -> Format {
+        Format { separator, skip_empty: false, comment_prefix: None, trim: false, lossy: false, invalid: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// Reads a few bytes at a time, so entries end up split over several reads.
+    fn entries(data: &str, format: &Format) -> Vec<String> {
+        valid_lines(io::BufReader::with_capacity(3, data.as_bytes()), format).map(Result::unwrap).collect()
+    }
+
+    fn lines(column: &Wordlist) -> Vec<String> {
+        (0..column.len()).map(|line| column.get(line).into_owned()).collect()
+    }
+
+    fn loaded(lines: &[&str]) -> Wordlist {
+        Wordlist::Loaded(lines.iter().map(|line| line.to_string()).collect())
+    }
+
+    #[test]
+    fn crlf_lines_lose_their_cr() {
+        assert_eq!(entries("a\r\nb\n\nc", &format(Separator::newline())), ["a", "b", "", "c"]);
+    }
+
+    #[test]
+    fn separators_can_be_several_bytes() {
+        assert_eq!(entries("a::b:c::::", &format(Separator::Bytes(b"::".to_vec()))), ["a", "b:c", ""]);
+        assert_eq!(entries("a\r\0b", &format(Separator::Bytes(vec![0]))), ["a\r", "b"]);
+    }
+
+    #[test]
+    fn paragraphs_are_split_on_blank_lines() {
+        assert_eq!(entries("\n\na\nb\n\n\nc\r\n\r\nd\n", &format(Separator::Paragraph)), ["a\nb", "c", "d"]);
+    }
+
+    #[test]
+    fn unique_drops_repeated_rows() {
+        let (columns, removed) = unique(vec![loaded(&["a", "b", "a", "b", "c"])]);
+        assert_eq!((lines(&columns[0]), removed), (vec!["a".to_string(), "b".to_string(), "c".to_string()], 2));
+        let (columns, removed) = unique(vec![loaded(&["a", "a", "a"]), loaded(&["1", "2", "1"])]);
+        assert_eq!((lines(&columns[0]), lines(&columns[1]), removed), (vec!["a".to_string(); 2], vec!["1".to_string(), "2".to_string()], 1));
+    }
+
+    #[test]
+    fn sort_orders_whole_rows() {
+        let sorted = sort(vec![loaded(&["b", "a", "b"]), loaded(&["2", "3", "1"])], Sort::Asc);
+        assert_eq!((lines(&sorted[0]), lines(&sorted[1])), (vec!["a".to_string(), "b".to_string(), "b".to_string()], vec!["3".to_string(), "1".to_string(), "2".to_string()]));
+        assert_eq!(lines(&sort(vec![loaded(&["b", "c", "a"])], Sort::Desc)[0]), ["c", "b", "a"]);
+        assert_eq!(lines(&sort(vec![loaded(&["x", "10 b", "9", " -1", "10 a"])], Sort::Numeric)[0]), [" -1", "9", "10 a", "10 b", "x"]);
+    }
+
+    #[test]
+    fn leading_numbers_like_sort_n() {
+        assert_eq!(leading_number("  12abc"), Some(12.0));
+        assert_eq!(leading_number("-3.5e1x"), Some(-35.0));
+        assert_eq!(leading_number("1e"), Some(1.0));
+        assert_eq!(leading_number("1.2.3"), Some(1.2));
+        assert_eq!(leading_number("abc"), None);
+        assert_eq!(leading_number("-"), None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs parel in a new directory holding `files`, so wordlists can be given by their names.
+fn parel(files: &[(&str, &str)], args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("parel-cli-{}-{}", std::process::id(), RUNS.fetch_add(1, Ordering::SeqCst)));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_parel")).args(args).current_dir(&dir).output().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn keep_order_prints_in_dispatch_order() {
+    let output = parel(&[("n.txt", "1\n2\n3\n4\n")], &["-t", "4", "-f", "n.txt:N", "--keep-order", "sleep 0.$((5 - N)); echo N"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1\n2\n3\n4\n");
+}
+
+#[test]
+fn grep_only_prints_matching_lines() {
+    let output = parel(&[("w.txt", "a\n")], &["-f", "w.txt:W", "--grep", "^x", "printf 'xW\\nyW\\nxW'"]);
+    assert_eq!(stdout(&output), "xa\nxa");
+}
+
+#[test]
+fn grep_v_drops_matching_lines() {
+    let output = parel(&[("w.txt", "a\n")], &["-f", "w.txt:W", "--grep-v", "^x", "printf 'xW\\nyW\\nyW'"]);
+    assert_eq!(stdout(&output), "ya\nya");
+}
+
+#[test]
+fn until_match_only_prints_the_winner() {
+    let output = parel(&[("n.txt", "1\n2\n9\n")], &["-t", "3", "-f", "n.txt:N", "--until-match", "^2$", "sleep 0.N; echo N"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n");
+    let output = parel(&[("n.txt", "1\n2\n9\n")], &["-t", "3", "-f", "n.txt:N", "--no-stdout", "--until-match", "^2$", "sleep 0.N; echo N"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn braced_combinations_are_numbered() {
+    let output = parel(&[("w.txt", "a\nb\nc\n")], &["-t", "1", "--braces", "-f", "w.txt:item", "--combinations", "2", "echo {item1}{item2}"]);
+    assert!(output.status.success());
+    let mut lines: Vec<&str> = std::str::from_utf8(&output.stdout).unwrap().lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["ab", "ac", "bc"]);
+}